@@ -0,0 +1,217 @@
+#[cfg(test)]
+mod tests;
+
+use crate::queries::types::CacheStats;
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How long responses are kept when `CACHE_TTL_SECONDS` isn't set
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Responses from GitHub and npm, kept for a while so that scoring the same repository or
+/// package again soon after doesn't query them again
+pub struct Cache {
+    ttl: Duration,
+    /// where entries are also written, so they outlive the server
+    dir: Option<PathBuf>,
+    entries: Mutex<HashMap<String, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    /// when expired entries were last removed, in seconds since the unix epoch
+    swept: AtomicU64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct Entry {
+    /// seconds since the unix epoch
+    #[serde(rename = "Stored")]
+    stored: u64,
+    #[serde(rename = "Value")]
+    value: String,
+}
+
+/// The response cache.
+/// Entries expire after `CACHE_TTL_SECONDS` (0 turns caching off), and are kept on disk in
+/// `CACHE_DIR` if it is set.
+pub fn get() -> &'static Cache {
+    static CACHE: OnceCell<Cache> = OnceCell::new();
+    CACHE.get_or_init(|| {
+        let ttl = std::env::var("CACHE_TTL_SECONDS")
+            .ok()
+            .and_then(|seconds| seconds.parse().ok())
+            .map_or(DEFAULT_TTL, Duration::from_secs);
+        let dir = std::env::var_os("CACHE_DIR").map(PathBuf::from);
+        Cache::new(ttl, dir)
+    })
+}
+
+impl Cache {
+    pub fn new(ttl: Duration, dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = &dir {
+            if let Err(e) = fs::create_dir_all(dir) {
+                log::error!("creating cache directory {}: {}", dir.display(), e);
+            }
+        }
+        Cache {
+            ttl,
+            dir,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            swept: AtomicU64::new(now()),
+        }
+    }
+
+    /// The value stored for `key`, if it hasn't expired. An expired entry is removed.
+    pub fn get(&self, key: &str) -> Option<String> {
+        let now = now();
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(key).cloned().or_else(|| self.read_entry(key));
+
+        match entry {
+            Some(entry) if self.fresh(&entry, now) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                let value = entry.value.clone();
+                entries.insert(key.to_owned(), entry);
+                Some(value)
+            }
+            expired => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                if expired.is_some() {
+                    entries.remove(key);
+                    self.remove_entry(key);
+                }
+                None
+            }
+        }
+    }
+
+    /// Store a value, and once every TTL remove the entries that expired without being looked up
+    pub fn insert(&self, key: &str, value: String) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let now = now();
+        if now.saturating_sub(self.swept.load(Ordering::Relaxed)) >= self.ttl.as_secs() {
+            self.swept.store(now, Ordering::Relaxed);
+            self.sweep(now);
+        }
+
+        let entry = Entry { stored: now, value };
+        self.write_entry(key, &entry);
+        self.entries.lock().unwrap().insert(key.to_owned(), entry);
+    }
+
+    /// Remove every expired entry, in memory and on disk
+    fn sweep(&self, now: u64) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, entry| self.fresh(entry, now));
+
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let files = fs::read_dir(dir).into_iter().flatten().flatten();
+        for file in files.filter(|f| f.path().extension().is_some_and(|e| e == "json")) {
+            let fresh = fs::read(file.path())
+                .ok()
+                .and_then(|text| serde_json::from_slice::<Entry>(&text).ok())
+                .is_some_and(|entry| self.fresh(&entry, now));
+            if !fresh {
+                remove_file(&file.path());
+            }
+        }
+    }
+
+    /// Remove every entry, returning how many were in memory
+    pub fn purge(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let purged = entries.len();
+        entries.clear();
+
+        if let Some(dir) = &self.dir {
+            let files = fs::read_dir(dir).into_iter().flatten().flatten();
+            for file in files.filter(|f| f.path().extension().is_some_and(|e| e == "json")) {
+                remove_file(&file.path());
+            }
+        }
+        purged
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+
+    fn fresh(&self, entry: &Entry, now: u64) -> bool {
+        now.saturating_sub(entry.stored) < self.ttl.as_secs()
+    }
+
+    fn read_entry(&self, key: &str) -> Option<Entry> {
+        let text = fs::read(entry_path(self.dir.as_deref()?, key)).ok()?;
+        serde_json::from_slice(&text).ok()
+    }
+
+    fn remove_entry(&self, key: &str) {
+        if let Some(dir) = &self.dir {
+            remove_file(&entry_path(dir, key));
+        }
+    }
+
+    fn write_entry(&self, key: &str, entry: &Entry) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let path = entry_path(dir, key);
+        let result = serde_json::to_vec(entry)
+            .map_err(std::io::Error::from)
+            .and_then(|text| fs::write(&path, text));
+        if let Err(e) = result {
+            log::error!("writing {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn remove_file(path: &Path) {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            log::error!("removing {}: {}", path.display(), e)
+        }
+        _ => (),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// File an entry is kept in, with anything in the key that isn't safe in a file name escaped
+fn entry_path(dir: &Path, key: &str) -> PathBuf {
+    let mut name = String::new();
+    for byte in key.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("_{:02x}", byte));
+        }
+    }
+    dir.join(name + ".json")
+}
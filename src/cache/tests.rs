@@ -0,0 +1,102 @@
+use super::*;
+
+use crate::queries::types::PackageId;
+
+#[test]
+fn hit_and_miss() {
+    let cache = Cache::new(Duration::from_secs(60), None);
+    assert_eq!(cache.get("npm/chalk"), None);
+    cache.insert("npm/chalk", "{}".to_owned());
+    assert_eq!(cache.get("npm/chalk").as_deref(), Some("{}"));
+
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 1,
+            entries: 1,
+        }
+    );
+}
+
+#[test]
+fn expired() {
+    let cache = Cache::new(Duration::from_secs(60), None);
+    cache.entries.lock().unwrap().insert(
+        "npm/chalk".to_owned(),
+        Entry {
+            stored: now() - 120,
+            value: "{}".to_owned(),
+        },
+    );
+    assert_eq!(cache.get("npm/chalk"), None);
+    assert_eq!(cache.stats().entries, 0);
+}
+
+#[test]
+fn zero_ttl_disables() {
+    let cache = Cache::new(Duration::ZERO, None);
+    cache.insert("npm/chalk", "{}".to_owned());
+    assert_eq!(cache.get("npm/chalk"), None);
+}
+
+#[test]
+fn on_disk() {
+    let dir = std::env::temp_dir().join(PackageId::new().as_ref());
+    let cache = Cache::new(Duration::from_secs(60), Some(dir.clone()));
+    cache.insert("npm/@babel/core", "{}".to_owned());
+    assert!(dir.join("npm_2f_40babel_2fcore.json").exists());
+
+    // as if the server restarted
+    let restarted = Cache::new(Duration::from_secs(60), Some(dir.clone()));
+    let found = restarted.get("npm/@babel/core");
+    let purged = restarted.purge();
+    let after_purge = restarted.get("npm/@babel/core");
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(found.as_deref(), Some("{}"));
+    assert_eq!(purged, 1);
+    assert_eq!(after_purge, None);
+}
+
+#[test]
+fn expired_removed_from_disk() {
+    let dir = std::env::temp_dir().join(PackageId::new().as_ref());
+    let cache = Cache::new(Duration::from_secs(60), Some(dir.clone()));
+    let old = Entry {
+        stored: now() - 120,
+        value: "{}".to_owned(),
+    };
+    cache.write_entry("npm/chalk", &old);
+    cache.write_entry("npm/left-pad", &old);
+
+    // looked up
+    let found = cache.get("npm/chalk");
+    let chalk_kept = entry_path(&dir, "npm/chalk").exists();
+    // never looked up again, but swept out once a TTL has passed since the last sweep
+    cache.swept.store(now() - 60, Ordering::Relaxed);
+    cache.insert("npm/react", "{}".to_owned());
+    let left_pad_kept = entry_path(&dir, "npm/left-pad").exists();
+    let react_kept = entry_path(&dir, "npm/react").exists();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(found, None);
+    assert!(!chalk_kept);
+    assert!(!left_pad_kept);
+    assert!(react_kept);
+}
+
+#[test]
+fn expired_swept_from_memory() {
+    let cache = Cache::new(Duration::from_secs(60), None);
+    cache.entries.lock().unwrap().insert(
+        "npm/chalk".to_owned(),
+        Entry {
+            stored: now() - 120,
+            value: "{}".to_owned(),
+        },
+    );
+    cache.swept.store(now() - 60, Ordering::Relaxed);
+    cache.insert("npm/react", "{}".to_owned());
+    assert_eq!(cache.stats().entries, 1);
+}
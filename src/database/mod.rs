@@ -1,13 +1,30 @@
-use crate::queries::types::{PackageMetadata, PackageRating};
+use crate::queries::types::{
+    ArchiveFormat, Job, JobId, PackageId, PackageMetadata, PackageRating, RatingRecord,
+};
 
-use firestore::FirestoreDb;
+use chrono::{DateTime, Utc};
+use firestore::{
+    FirestoreDb, FirestoreDocument, FirestoreResult, FirestoreStreamingBatchWriteOptions,
+    FirestoreStreamingBatchWriter,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[cfg(not(test))]
 pub const METADATA: &str = "metadata";
 #[cfg(test)]
 pub const METADATA: &str = "metadata-test";
 
+#[cfg(not(test))]
+pub const RATING_HISTORY: &str = "rating-history";
+#[cfg(test)]
+pub const RATING_HISTORY: &str = "rating-history-test";
+
+#[cfg(not(test))]
+pub const JOBS: &str = "jobs";
+#[cfg(test)]
+pub const JOBS: &str = "jobs-test";
+
 #[cfg(not(test))]
 pub const PAGE_LIMIT: usize = 10;
 #[cfg(test)]
@@ -23,14 +40,158 @@ pub struct DatabaseEntry {
     pub metadata: PackageMetadata,
     #[serde(rename = "URL")]
     pub url: String,
+    /// SPDX expression the package declares, checked when other packages depend on it
+    #[serde(rename = "License", default)]
+    pub license: Option<String>,
+    /// bytes of the stored package, 0 for packages stored before sizes were recorded
+    #[serde(rename = "Size", default)]
+    pub size: u64,
+    /// commit the package was downloaded at, if it came from a repository that says
+    #[serde(rename = "Commit", default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// SRI digest the registry tarball was verified against, for packages ingested from npm
+    #[serde(rename = "Integrity", default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+    /// format of the archive at `url`
+    #[serde(rename = "Format", default)]
+    pub format: ArchiveFormat,
+    /// a zip of the package kept as well, when `url` is its original tarball
+    #[serde(rename = "ZipURL", default, skip_serializing_if = "Option::is_none")]
+    pub zip_url: Option<String>,
+    /// the `dependencies` of its `package.json`, names to version requirements
+    #[serde(rename = "Dependencies", default)]
+    pub dependencies: HashMap<String, String>,
+    /// names of the packages the dependencies refer to, to look up dependents with
+    #[serde(rename = "DependencyNames", default)]
+    pub dependency_names: Vec<String>,
+    /// the job that ingested it, so that job can tell it already finished if it's run again
+    #[serde(rename = "Job", default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<JobId>,
+    #[serde(flatten)]
+    pub rating: PackageRating,
+}
+
+/// A rating of a package at some point in time
+#[derive(Deserialize, Serialize)]
+pub struct HistoryEntry {
+    #[serde(rename = "ID")]
+    pub id: PackageId,
+    #[serde(rename = "Timestamp", with = "firestore::serialize_as_timestamp")]
+    pub timestamp: DateTime<Utc>,
+    /// version of the scoring policy that computed the rating
+    #[serde(rename = "PolicyVersion", default)]
+    pub policy_version: u32,
     #[serde(flatten)]
     pub rating: PackageRating,
 }
 
+impl HistoryEntry {
+    /// A rating computed just now by the current scoring policy
+    pub fn now(id: PackageId, rating: PackageRating) -> Self {
+        HistoryEntry {
+            id,
+            timestamp: Utc::now(),
+            policy_version: crate::scoring::POLICY_VERSION,
+            rating,
+        }
+    }
+}
+
+impl From<HistoryEntry> for RatingRecord {
+    fn from(
+        HistoryEntry {
+            timestamp,
+            policy_version,
+            rating,
+            ..
+        }: HistoryEntry,
+    ) -> Self {
+        RatingRecord {
+            timestamp,
+            policy_version,
+            rating,
+        }
+    }
+}
+
+/// Keep a rating in the history of the package's ratings
+pub async fn record_rating(
+    db: &FirestoreDb,
+    entry: &HistoryEntry,
+) -> firestore::FirestoreResult<()> {
+    db.fluent()
+        .insert()
+        .into(RATING_HISTORY)
+        .generate_document_id()
+        .object(entry)
+        .execute::<()>()
+        .await
+}
+
+/// Delete documents of `collection` in one batch
+pub async fn delete_documents(
+    db: &FirestoreDb,
+    collection: &str,
+    documents: Vec<FirestoreDocument>,
+) -> FirestoreResult<()> {
+    if documents.is_empty() {
+        return Ok(());
+    }
+    let (batch_stream, _) =
+        FirestoreStreamingBatchWriter::new(db.clone(), FirestoreStreamingBatchWriteOptions::new())
+            .await?;
+
+    let mut batch = batch_stream.new_batch();
+    for document in documents {
+        // the ID a document was stored under is the last part of its name
+        let id = document.name.rsplit('/').next().unwrap_or_default();
+        batch.delete_by_id(collection, id, None)?;
+    }
+
+    batch.write().await?;
+    batch_stream.finish().await;
+    Ok(())
+}
+
+/// A job, along with what it needs to be run again if the server restarts
+#[derive(Deserialize, Serialize)]
+pub struct JobEntry {
+    #[serde(flatten)]
+    pub job: Job,
+    /// the URL to ingest from, or `None` if the content was uploaded to storage for the job
+    #[serde(rename = "URL")]
+    pub url: Option<String>,
+    /// the version or dist-tag asked for along with the URL
+    #[serde(rename = "Version", default)]
+    pub version: Option<String>,
+    /// the tag, branch or commit asked for along with the URL
+    #[serde(rename = "Ref", default)]
+    pub reference: Option<String>,
+    #[serde(rename = "Created", with = "firestore::serialize_as_timestamp")]
+    pub created: DateTime<Utc>,
+    /// until when the instance running the job has it to itself
+    #[serde(
+        rename = "Lease",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "firestore::serialize_as_optional_timestamp"
+    )]
+    pub lease: Option<DateTime<Utc>>,
+}
+
 pub const NAME: &str = "Name";
 pub const VERSION: &str = "Version";
 pub const ID: &str = "ID";
 pub const URL: &str = "URL";
+pub const LICENSE: &str = "License";
+pub const SIZE: &str = "Size";
+pub const COMMIT: &str = "Commit";
+pub const INTEGRITY: &str = "Integrity";
+pub const FORMAT: &str = "Format";
+pub const ZIP_URL: &str = "ZipURL";
+pub const DEPENDENCIES: &str = "Dependencies";
+pub const DEPENDENCY_NAMES: &str = "DependencyNames";
+pub const JOB: &str = "Job";
 
 pub const NET_SCORE: &str = "NetScore";
 pub const BUS_FACTOR: &str = "BusFactor";
@@ -40,3 +201,10 @@ pub const RESPONSIVE_MAINTAINER: &str = "ResponsiveMaintainer";
 pub const LICENSE_SCORE: &str = "LicenseScore";
 pub const GOOD_PINNING_PRACTICE: &str = "GoodPinningPractice";
 pub const PULL_REQUEST: &str = "PullRequest";
+
+pub const STATE: &str = "State";
+pub const PACKAGE: &str = "Package";
+pub const ERROR: &str = "Error";
+pub const LEASE: &str = "Lease";
+pub const INCOMPATIBLE_LICENSES: &str = "IncompatibleLicenses";
+pub const LICENSE_CONFLICTS: &str = "LicenseConflicts";
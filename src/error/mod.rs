@@ -0,0 +1,148 @@
+mod extract;
+#[cfg(test)]
+mod tests;
+
+pub use extract::{Json, Path, Query};
+
+use crate::{scoring::RatingError, storage::StorageError};
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use firestore::errors::FirestoreError;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Why a request failed, sent to the client as `{"code": ..., "message": ..., "details": ...}`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    /// name of the kind of error, for clients to match on
+    pub code: String,
+    pub message: String,
+    /// anything else known about the error, or `null`
+    pub details: Option<Value>,
+    /// whole seconds to wait before trying again, sent as `Retry-After`
+    #[serde(skip)]
+    pub retry_after: Option<u64>,
+}
+
+impl ApiError {
+    /// An error whose code is the name of its status, like `NotFound`
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code: status_code_name(status),
+            message: message.into(),
+            details: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = code.into();
+        self
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// A server error whose cause is only logged, since it can name internal resources
+    fn internal(code: &str, message: &str, cause: impl std::fmt::Display) -> Self {
+        log::error!("{}: {}", message, cause);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message).with_code(code)
+    }
+}
+
+/// `Not Found` => `NotFound`
+fn status_code_name(status: StatusCode) -> String {
+    status
+        .canonical_reason()
+        .unwrap_or("Unknown")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        ApiError::new(status, status.canonical_reason().unwrap_or_default())
+    }
+}
+
+/// Some of the errors returned by scoring are server errors, some are because of a bad request,
+/// and some because GitHub's rate limit ran out.
+/// Only the messages of bad requests are sent, since the rest can name files, upstream URLs or
+/// what git printed.
+impl From<RatingError> for ApiError {
+    fn from(e: RatingError) -> Self {
+        use RatingError::*;
+        let status = match e {
+            MissingPackageJson
+            | MissingRepository
+            | UrlParseError(_)
+            | FieldNotApplicable { .. }
+            | HostNotAllowed(_) => StatusCode::BAD_REQUEST,
+            NpmVersionNotFound { .. } | GithubRefNotFound { .. } | RepositoryNotFound { .. } => {
+                StatusCode::NOT_FOUND
+            }
+            // the registry served something other than what it said it would
+            IntegrityMismatch { .. } => StatusCode::BAD_GATEWAY,
+            _ if e.retry_after().is_some() => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        if status.is_client_error() {
+            return ApiError::new(status, e.to_string()).with_code(e.kind());
+        }
+        let message = match status {
+            StatusCode::BAD_GATEWAY => "the registry's tarball does not match its digest",
+            StatusCode::SERVICE_UNAVAILABLE => "GitHub rate limit exhausted",
+            _ => return ApiError::internal(e.kind(), "scoring failed", e),
+        };
+        log::error!("{}: {}", message, e);
+
+        // rounded up to whole seconds
+        let retry_after = e
+            .retry_after()
+            .map(|wait| wait.as_secs() + u64::from(wait.subsec_nanos() > 0));
+        let error = ApiError {
+            retry_after,
+            ..ApiError::new(status, message).with_code(e.kind())
+        };
+        match retry_after {
+            Some(seconds) => error.with_details(serde_json::json!({ "retry_after": seconds })),
+            None => error,
+        }
+    }
+}
+
+impl From<FirestoreError> for ApiError {
+    fn from(e: FirestoreError) -> Self {
+        ApiError::internal("DatabaseError", "database request failed", e)
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(e: StorageError) -> Self {
+        ApiError::internal("StorageError", "storage request failed", e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status;
+        match self.retry_after {
+            Some(seconds) => (
+                status,
+                [(header::RETRY_AFTER, seconds.to_string())],
+                axum::Json(self),
+            )
+                .into_response(),
+            None => (status, axum::Json(self)).into_response(),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use super::*;
+
+use axum::{body::Body, extract::FromRequest, http::Request};
+use std::io;
+
+#[test]
+fn status_names() {
+    assert_eq!(status_code_name(StatusCode::NOT_FOUND), "NotFound");
+    assert_eq!(
+        status_code_name(StatusCode::FAILED_DEPENDENCY),
+        "FailedDependency"
+    );
+    assert_eq!(
+        status_code_name(StatusCode::INTERNAL_SERVER_ERROR),
+        "InternalServerError"
+    );
+}
+
+#[test]
+fn body_shape() {
+    let error = ApiError::new(StatusCode::CONFLICT, "package `chalk` already exists");
+    assert_eq!(
+        serde_json::to_value(&error).unwrap(),
+        serde_json::json!({
+            "code": "Conflict",
+            "message": "package `chalk` already exists",
+            "details": null,
+        })
+    );
+}
+
+#[test]
+fn from_rating_error() {
+    let error = ApiError::from(RatingError::MissingRepository);
+    assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.code, "MissingRepository");
+    assert_eq!(error.message, RatingError::MissingRepository.to_string());
+
+    let error = ApiError::from(RatingError::NpmVersionNotFound {
+        name: "chalk".to_owned(),
+        requested: "next".to_owned(),
+    });
+    assert_eq!(error.status, StatusCode::NOT_FOUND);
+    assert_eq!(error.code, "NpmVersionNotFound");
+}
+
+#[test]
+fn scoring_causes_not_sent() {
+    let e = io::Error::new(io::ErrorKind::NotFound, "/tmp/abc/package.json missing");
+    let error = ApiError::from(RatingError::IoError(e));
+    assert_eq!(error.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(error.code, "IoError");
+    assert_eq!(error.message, "scoring failed");
+    assert_eq!(error.details, None);
+}
+
+#[test]
+fn retry_after_header() {
+    let error = ApiError {
+        retry_after: Some(91),
+        ..ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "GitHub rate limit exhausted",
+        )
+    };
+    let response = error.into_response();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "91");
+}
+
+#[test]
+fn internal_causes_not_sent() {
+    let error = ApiError::internal(
+        "StorageError",
+        "storage request failed",
+        "bucket `private-bucket` not found",
+    );
+    assert_eq!(error.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(error.code, "StorageError");
+    assert_eq!(error.details, None);
+    assert!(!error.message.contains("private-bucket"));
+}
+
+#[tokio::test]
+async fn malformed_body() {
+    let request = Request::builder()
+        .method("POST")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{\"Name\":"))
+        .unwrap();
+    let error = match Json::<Value>::from_request(request, &()).await {
+        Ok(_) => panic!("malformed body accepted"),
+        Err(e) => e,
+    };
+    assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.code, "InvalidBody");
+    assert!(!error.message.is_empty());
+}
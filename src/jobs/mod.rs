@@ -0,0 +1,310 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    database::{self, DatabaseEntry, JobEntry},
+    error::ApiError,
+    queries::{
+        ingest,
+        types::{Job, JobError, JobId, JobState, Package, PackageData},
+    },
+    storage::CloudStorage,
+};
+
+use chrono::{DateTime, Utc};
+use firestore::{errors::FirestoreError, timestamp_utils, FirestoreDb, FirestoreWritePrecondition};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// Jobs waiting for the worker
+static QUEUE: OnceCell<mpsc::UnboundedSender<JobId>> = OnceCell::new();
+
+/// States of a job that hasn't finished
+const UNFINISHED: [JobState; 4] = [
+    JobState::Queued,
+    JobState::Rating,
+    JobState::Uploading,
+    JobState::Storing,
+];
+
+/// Minutes an instance has a job to itself once it starts running it. A job whose lease ran out
+/// was left unfinished by an instance that stopped, so another may take it.
+const LEASE_MINUTES: i64 = 30;
+
+/// Start running jobs in the background, one at a time.
+/// Jobs that hadn't finished when the server last stopped are run again first.
+pub fn start_worker() {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    if QUEUE.set(sender).is_err() {
+        return;
+    }
+    tokio::spawn(async move {
+        let db = database::get_database().await;
+        resume(&db).await;
+        while let Some(id) = receiver.recv().await {
+            if let Err(e) = run(&db, &id).await {
+                log::error!("running job `{}`: {}", id.as_ref(), e);
+            }
+        }
+    });
+}
+
+/// Save a job to ingest `data` and queue it
+pub async fn submit(data: PackageData) -> Result<Job, ApiError> {
+    let id = JobId::new();
+    let (url, version, reference) = match data {
+        PackageData::Url {
+            url,
+            version,
+            reference,
+        } => (Some(url), version, reference),
+        // too big to keep in the job's document
+        PackageData::Content { content } => {
+            let storage = CloudStorage::new().await?;
+            storage
+                .put_object(content_object(&id), content.into_bytes())
+                .await?;
+            (None, None, None)
+        }
+    };
+
+    let entry = JobEntry {
+        job: Job {
+            id: id.clone(),
+            state: JobState::Queued,
+            package: None,
+            error: None,
+        },
+        url,
+        version,
+        reference,
+        created: Utc::now(),
+        lease: None,
+    };
+    let db = database::get_database().await;
+    db.fluent()
+        .insert()
+        .into(database::JOBS)
+        .document_id(id.as_ref())
+        .object(&entry)
+        .execute::<()>()
+        .await?;
+
+    enqueue(id);
+    Ok(entry.job)
+}
+
+/// Record how far along a job is, if ingestion is being run as one
+pub async fn set_state(db: &FirestoreDb, job: Option<&JobId>, state: JobState) {
+    #[derive(Deserialize, Serialize)]
+    struct StateUpdate {
+        #[serde(rename = "State")]
+        state: JobState,
+    }
+
+    let Some(id) = job else {
+        return;
+    };
+    let result = db
+        .fluent()
+        .update()
+        .fields([database::STATE])
+        .in_col(database::JOBS)
+        .document_id(id.as_ref())
+        .object(&StateUpdate { state })
+        .execute::<()>()
+        .await;
+    if let Err(e) = result {
+        log::error!("updating state of job `{}`: {}", id.as_ref(), e);
+    }
+}
+
+fn enqueue(id: JobId) {
+    match QUEUE.get() {
+        Some(queue) => {
+            if queue.send(id).is_err() {
+                log::error!("job worker has stopped");
+            }
+        }
+        None => log::error!("job worker was never started"),
+    }
+}
+
+/// Queue the jobs that were saved but never finished, oldest first.
+/// Every instance does this when it starts, so they claim each job before running it.
+async fn resume(db: &FirestoreDb) {
+    let result: firestore::FirestoreResult<Vec<JobEntry>> = db
+        .fluent()
+        .select()
+        .from(database::JOBS)
+        .filter(|q| q.field(database::STATE).is_in(UNFINISHED))
+        .obj()
+        .query()
+        .await;
+    let mut entries = match result {
+        Ok(entries) => entries,
+        Err(e) => return log::error!("reading unfinished jobs: {}", e),
+    };
+
+    entries.sort_by_key(|entry| entry.created);
+    for entry in entries {
+        enqueue(entry.job.id);
+    }
+}
+
+async fn run(db: &FirestoreDb, id: &JobId) -> Result<(), Box<dyn std::error::Error>> {
+    // finished, or being run by another instance
+    let Some(entry) = claim(db, id).await? else {
+        return Ok(());
+    };
+
+    let uploaded = entry.url.is_none();
+    let result = match stored_by(db, id).await {
+        // the instance that ran it before stopped after storing the package
+        Ok(Some(package)) => Ok(package),
+        // a job whose content can't be read fails, rather than being queued again on every
+        // restart
+        Ok(None) => match job_data(id, entry).await {
+            Ok(data) => ingest(data, Some(id)).await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e.into()),
+    };
+
+    let job = finished(id.clone(), result.map_err(Into::into));
+    db.fluent()
+        .update()
+        .fields([database::STATE, database::PACKAGE, database::ERROR])
+        .in_col(database::JOBS)
+        .document_id(id.as_ref())
+        .object(&job)
+        .execute::<()>()
+        .await?;
+
+    if uploaded {
+        let result = match CloudStorage::new().await {
+            Ok(storage) => storage.delete_object(content_object(id)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            log::error!("deleting content of job `{}`: {}", id.as_ref(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Take a job for this instance to run, unless it has finished or another instance has it.
+/// The lease is only written if the job hasn't changed since it was read, so when instances
+/// claim a job at once, one of them gets it.
+async fn claim(
+    db: &FirestoreDb,
+    id: &JobId,
+) -> Result<Option<JobEntry>, Box<dyn std::error::Error>> {
+    #[derive(Deserialize, Serialize)]
+    struct LeaseUpdate {
+        #[serde(rename = "Lease", with = "firestore::serialize_as_timestamp")]
+        lease: DateTime<Utc>,
+    }
+
+    let document = db
+        .fluent()
+        .select()
+        .by_id_in(database::JOBS)
+        .one(id.as_ref())
+        .await?
+        .ok_or("job does not exist")?;
+    let mut entry: JobEntry = FirestoreDb::deserialize_doc_to(&document)?;
+    let now = Utc::now();
+    if !claimable(&entry, now) {
+        return Ok(None);
+    }
+    let read_at = document.update_time.ok_or("job has no update time")?;
+
+    let update = LeaseUpdate {
+        lease: now + chrono::Duration::minutes(LEASE_MINUTES),
+    };
+    let result = db
+        .fluent()
+        .update()
+        .fields([database::LEASE])
+        .in_col(database::JOBS)
+        .precondition(FirestoreWritePrecondition::UpdateTime(
+            timestamp_utils::from_timestamp(read_at)?,
+        ))
+        .document_id(id.as_ref())
+        .object(&update)
+        .execute::<()>()
+        .await;
+    match result {
+        Ok(()) => {
+            entry.lease = Some(update.lease);
+            Ok(Some(entry))
+        }
+        // changed since it was read, by another instance claiming it
+        Err(FirestoreError::DatabaseError(e)) if e.public.code == "FailedPrecondition" => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether a job is waiting for an instance to run it
+fn claimable(entry: &JobEntry, now: DateTime<Utc>) -> bool {
+    UNFINISHED.contains(&entry.job.state) && entry.lease.map_or(true, |lease| lease <= now)
+}
+
+/// The package a job already stored, if it has
+async fn stored_by(db: &FirestoreDb, id: &JobId) -> Result<Option<Package>, ApiError> {
+    let stored: Vec<DatabaseEntry> = db
+        .fluent()
+        .select()
+        .from(database::METADATA)
+        .filter(|q| q.field(database::JOB).eq(id.as_ref()))
+        .limit(1)
+        .obj()
+        .query()
+        .await?;
+    Ok(stored.into_iter().next().map(Package::from))
+}
+
+/// What a job is to ingest, with any content that was uploaded for it read back from storage
+async fn job_data(id: &JobId, entry: JobEntry) -> Result<PackageData, ApiError> {
+    Ok(match entry.url {
+        Some(url) => PackageData::Url {
+            url,
+            version: entry.version,
+            reference: entry.reference,
+        },
+        None => {
+            let content = CloudStorage::new()
+                .await?
+                .get_object(content_object(id))
+                .await?;
+            PackageData::Content {
+                content: String::from_utf8_lossy(&content).into_owned(),
+            }
+        }
+    })
+}
+
+/// The job as it is once ingestion has finished
+fn finished(id: JobId, result: Result<Package, JobError>) -> Job {
+    match result {
+        Ok(package) => Job {
+            id,
+            state: JobState::Succeeded,
+            package: Some(package),
+            error: None,
+        },
+        Err(error) => Job {
+            id,
+            state: JobState::Failed,
+            package: None,
+            error: Some(error),
+        },
+    }
+}
+
+/// Name of the storage object holding the content uploaded for a job
+fn content_object(id: &JobId) -> String {
+    format!("job-{}", id.as_ref())
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+use crate::queries::types::{PackageId, PackageMetadata};
+
+#[test]
+fn finished_succeeded() {
+    let id = JobId::new();
+    let package = Package {
+        metadata: PackageMetadata {
+            name: "chalk".to_owned(),
+            version: "5.2.0".parse().unwrap(),
+            id: PackageId::new(),
+        },
+        data: PackageData::Url {
+            url: "https://www.npmjs.com/package/chalk".to_owned(),
+            version: None,
+            reference: None,
+        },
+    };
+    let job = finished(id.clone(), Ok(package));
+    assert_eq!(job.id, id);
+    assert_eq!(job.state, JobState::Succeeded);
+    assert!(job.package.is_some());
+    assert_eq!(job.error, None);
+}
+
+#[test]
+fn finished_failed() {
+    let error = JobError {
+        status: 400,
+        kind: Some("MissingRepository".to_owned()),
+        retry_after: None,
+        message: "missing repository url".to_owned(),
+    };
+    let job = finished(JobId::new(), Err(error));
+    assert_eq!(job.state, JobState::Failed);
+    assert_eq!(job.package, None);
+    assert_eq!(job.error.map(|e| e.status), Some(400));
+}
+
+#[test]
+fn content_object_name() {
+    let id = JobId::new();
+    assert_eq!(content_object(&id), format!("job-{}", id.as_ref()));
+}
+
+#[tokio::test]
+async fn url_job_data() {
+    let id = JobId::new();
+    let entry = JobEntry {
+        job: Job {
+            id: id.clone(),
+            state: JobState::Queued,
+            package: None,
+            error: None,
+        },
+        url: Some("https://www.npmjs.com/package/chalk".to_owned()),
+        version: Some("5.2.0".to_owned()),
+        reference: None,
+        created: Utc::now(),
+        lease: None,
+    };
+    let data = job_data(&id, entry).await.unwrap();
+    assert_eq!(
+        data,
+        PackageData::Url {
+            url: "https://www.npmjs.com/package/chalk".to_owned(),
+            version: Some("5.2.0".to_owned()),
+            reference: None,
+        }
+    );
+}
+
+#[test]
+fn claimable_jobs() {
+    let now = Utc::now();
+    let entry = |state, lease| JobEntry {
+        job: Job {
+            id: JobId::new(),
+            state,
+            package: None,
+            error: None,
+        },
+        url: None,
+        version: None,
+        reference: None,
+        created: now,
+        lease,
+    };
+    let minute = chrono::Duration::minutes(1);
+
+    assert!(claimable(&entry(JobState::Queued, None), now));
+    // left by an instance that stopped
+    assert!(claimable(&entry(JobState::Rating, Some(now - minute)), now));
+    // being run by another instance
+    assert!(!claimable(
+        &entry(JobState::Rating, Some(now + minute)),
+        now
+    ));
+    assert!(!claimable(&entry(JobState::Succeeded, None), now));
+    assert!(!claimable(&entry(JobState::Failed, None), now));
+}
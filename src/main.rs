@@ -1,5 +1,9 @@
+mod cache;
 mod database;
+mod error;
+mod jobs;
 mod queries;
+mod rescore;
 mod scoring;
 mod storage;
 mod user;
@@ -55,18 +59,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         )
         .route("/packages", post(search_packages))
         .route("/package/:id/rate", get(get_rating_by_id))
+        .route("/package/:id/rate/history", get(get_rating_history_by_id))
+        .route("/package/:id/cost", get(get_cost_by_id))
+        .route("/package/:id/dependencies", get(get_dependencies_by_id))
+        .route("/package/:id/dependents", get(get_dependents_by_id))
         .route("/authenticate", put(authenticate))
         .route(
             "/package/byName/:name",
             get(get_package_by_name).delete(delete_package_by_name),
         )
         .route("/package/byRegEx", get(get_package_by_regex))
+        .route("/admin/cache", get(get_cache_stats).delete(purge_cache))
+        .route("/jobs/:id", get(get_job_by_id))
         .route("/reset", delete(reset_registry))
+        .route("/admin/rescore", post(rescore_registry))
         .layer(cors);
 
     #[cfg(feature = "log_request_response")]
     let app = app.layer(axum::middleware::from_fn(log::print_request_response));
 
+    rescore::schedule();
+    jobs::start_worker();
+
     axum::Server::bind(&SocketAddr::new(
         IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
         8080,
@@ -1,26 +1,26 @@
 use firestore::FirestoreDb;
-use futures::FutureExt;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use tokio::join;
 
 use super::{ok, respond, types::*, MyResponse};
 use crate::{
-    database::{self, DatabaseEntry},
-    scoring::{self, RatedPackage, RatingError},
-    storage::CloudStorage,
+    database::{self, DatabaseEntry, HistoryEntry},
+    error::{ApiError, Json, Path, Query},
+    jobs,
+    scoring::{self, RatedPackage},
+    storage::{CloudStorage, NpmArchives},
 };
 
 use axum::{
-    extract::{Json, Path},
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
 };
+use serde_json::json;
 
 const MIN_ALLOWED_NET_SCORE: f64 = 0.5;
 
-async fn find_package_by_id<F, T>(
-    db: &FirestoreDb,
-    id: PackageId,
-    fields: F,
-) -> Result<T, StatusCode>
+async fn find_package_by_id<F, T>(db: &FirestoreDb, id: PackageId, fields: F) -> Result<T, ApiError>
 where
     F: IntoIterator,
     F::Item: AsRef<str>,
@@ -34,43 +34,64 @@ where
         .limit(1)
         .filter(|q| q.field(database::ID).eq(&id));
 
-    let query_result = query.obj().query().await.map_err(|e| {
-        log::error!("{}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let query_result: Vec<T> = query.obj().query().await?;
 
-    query_result.into_iter().next().ok_or(StatusCode::NOT_FOUND)
+    query_result.into_iter().next().ok_or_else(|| {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("no package with ID `{}`", id.as_ref()),
+        )
+    })
 }
 
-/// Some of the errors returned by scoring are server errors, some are because of a bad request
-/// This takes the rating error and transforms it into the appropriate status code (400 or 500)
-fn scoring_err_to_response(e: RatingError) -> StatusCode {
-    log::error!("scoring error: {:?}", e);
-    use RatingError::*;
-    match e {
-        MissingPackageJson | MissingRepository | UrlParseError(_) => StatusCode::BAD_REQUEST,
-        _ => StatusCode::INTERNAL_SERVER_ERROR,
+/// Keep a newly computed rating in the package's history.
+/// The package itself was already stored, so failing to do this isn't an error for the request.
+async fn record_rating(db: &FirestoreDb, id: PackageId, rating: PackageRating) {
+    if let Err(e) = database::record_rating(db, &HistoryEntry::now(id, rating)).await {
+        log::error!("recording rating history: {}", e);
     }
 }
 
+/// Names of the packages in the registry that the dependencies refer to, without duplicates
+fn dependency_names(dependencies: &HashMap<String, String>) -> Vec<String> {
+    let names: BTreeSet<_> = dependencies
+        .iter()
+        .filter_map(|(name, spec)| scoring::registry_requirement(name, spec))
+        .map(|(name, _)| name.to_owned())
+        .collect();
+    names.into_iter().collect()
+}
+
 /// Interact with the package with this ID
 ///
 /// Return this package.
 pub async fn get_package_by_id(
     Path(id): Path<PackageId>,
-) -> Result<MyResponse<Package>, StatusCode> {
+) -> Result<MyResponse<StoredPackage>, ApiError> {
     // 200: return package
     // 404: does not exist
     // note: this doesn't actually meet the spec
     // they want to have the content when downloading, we're giving a url
     // exodus is expensive from api, cheap from obj storage
     // they can just download it directly from there
+    #[derive(Deserialize)]
+    struct WithData {
+        #[serde(flatten)]
+        metadata: PackageMetadata,
+        #[serde(flatten)]
+        data: StoredPackageData,
+    }
+
     let db = database::get_database().await;
-    let PackageWithUrl { metadata, url } = find_package_by_id(&db, id, PACKAGE_FIELDS).await?;
-    Ok(ok(Package {
-        metadata,
-        data: PackageData::Url { url },
-    }))
+    let WithData { metadata, data } = find_package_by_id(
+        &db,
+        id,
+        PACKAGE_FIELDS
+            .iter()
+            .chain([&database::FORMAT, &database::ZIP_URL]),
+    )
+    .await?;
+    Ok(ok(StoredPackage { metadata, data }))
 }
 
 /// Update the content of the package.
@@ -80,28 +101,43 @@ pub async fn get_package_by_id(
 pub async fn update_package_by_id(
     Path(path_id): Path<PackageId>,
     Json(Package { metadata, data }): Json<Package>,
-) -> Result<(), StatusCode> {
+) -> Result<(), ApiError> {
     // if they put an id in the metadata, it should match the one they put in the path
     if metadata.id.as_ref() != "" && path_id != metadata.id {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "the ID in the metadata does not match the one in the path",
+        ));
     }
 
     let db = database::get_database().await;
 
     let previous: PackageWithUrl = find_package_by_id(&db, path_id, PACKAGE_FIELDS).await?;
     if previous.metadata.name != metadata.name || previous.metadata.version != metadata.version {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!(
+                "no package `{}` {} with ID `{}`",
+                metadata.name,
+                metadata.version,
+                previous.metadata.id.as_ref()
+            ),
+        ));
     }
 
     let RatedPackage {
         name,
         version,
+        license,
+        dependencies,
         rating,
         content,
+        content_size,
+        commit,
+        integrity,
+        tarball,
         ..
-    } = scoring::rate_package(data)
-        .await
-        .map_err(scoring_err_to_response)?;
+    } = scoring::rate_package(data).await?;
 
     if name != metadata.name || version != metadata.version {
         // trying to upload wrong package
@@ -118,66 +154,204 @@ pub async fn update_package_by_id(
             version,
             previous.metadata.version
         );
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "the package contents are for a different name or version",
+        )
+        .with_details(json!({ "name": name, "version": version.to_string() })));
     }
 
-    if rating.net_score < MIN_ALLOWED_NET_SCORE {
-        return Err(StatusCode::FAILED_DEPENDENCY);
-    }
+    check_net_score(rating.net_score)?;
 
     // upload to obj storage
-    let storage = CloudStorage::new().await.map_err(|e| {
-        log::error!("cloud storage handle error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    let url = storage
-        .put_object(previous.metadata.id.as_ref().to_owned(), content)
-        .await
-        .map_err(|e| {
-            log::error!("cloud storage put error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let StoredArchives {
+        url,
+        size,
+        format,
+        zip_url,
+    } = store_archives(&previous.metadata.id, content, content_size, tarball, true).await?;
 
     let entry = DatabaseEntry {
         metadata,
         url,
+        license,
+        size,
+        commit,
+        integrity,
+        format,
+        zip_url,
+        dependency_names: dependency_names(&dependencies),
+        dependencies,
+        // not updated
+        job: None,
         rating,
     };
 
     db.fluent()
         .update()
-        .fields(RATING_FIELDS.iter().chain([&database::URL]))
+        .fields(RATING_FIELDS.iter().chain([
+            &database::URL,
+            &database::LICENSE,
+            &database::SIZE,
+            &database::COMMIT,
+            &database::INTEGRITY,
+            &database::FORMAT,
+            &database::ZIP_URL,
+            &database::DEPENDENCIES,
+            &database::DEPENDENCY_NAMES,
+        ]))
         .in_col(database::METADATA)
-        .document_id(previous.metadata.id)
+        .document_id(&previous.metadata.id)
         .object(&entry)
-        .execute()
-        .await
-        .map_err(|e| {
-            log::error!("{}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .execute::<()>()
+        .await?;
+
+    record_rating(&db, previous.metadata.id, entry.rating).await;
 
     Ok(())
 }
 
+#[derive(Debug, Deserialize)]
+pub struct IngestQuery {
+    /// run ingestion as a job in the background, instead of during the request
+    #[serde(rename = "async", default)]
+    run_async: bool,
+}
+
 pub async fn post_package(
+    Query(IngestQuery { run_async }): Query<IngestQuery>,
     Json(data): Json<PackageData>,
-) -> Result<MyResponse<Package>, StatusCode> {
+) -> Result<Response, ApiError> {
+    if run_async {
+        let job = jobs::submit(data).await?;
+        let location = HeaderValue::from_str(&format!("/jobs/{}", job.id.as_ref()))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        // 202: return job to poll
+        return Ok(respond(StatusCode::ACCEPTED, job)
+            .push_header((header::LOCATION, location))
+            .into_response());
+    }
+
+    let package = ingest(data, None).await?;
+    // 201: return package
+    Ok(respond(StatusCode::CREATED, package).into_response())
+}
+
+impl From<ApiError> for JobError {
+    fn from(
+        ApiError {
+            status,
+            code,
+            message,
+            retry_after,
+            ..
+        }: ApiError,
+    ) -> Self {
+        JobError {
+            status: status.as_u16(),
+            kind: Some(code),
+            retry_after,
+            message,
+        }
+    }
+}
+
+/// Packages rated below this aren't let into the registry
+fn check_net_score(net_score: f64) -> Result<(), ApiError> {
+    if net_score < MIN_ALLOWED_NET_SCORE {
+        return Err(ApiError::new(
+            StatusCode::FAILED_DEPENDENCY,
+            format!(
+                "net score {} is below the minimum of {}",
+                net_score, MIN_ALLOWED_NET_SCORE
+            ),
+        )
+        .with_details(json!({ "net_score": net_score, "minimum": MIN_ALLOWED_NET_SCORE })));
+    }
+    Ok(())
+}
+
+/// Where a package's archives were uploaded
+struct StoredArchives {
+    url: String,
+    /// bytes of the archive at `url`, once decoded
+    size: u64,
+    format: ArchiveFormat,
+    zip_url: Option<String>,
+}
+
+/// Name of the object holding the zip of a package whose main archive is its tarball
+fn zip_object(id: &PackageId) -> String {
+    format!("{}.zip", id.as_ref())
+}
+
+/// Upload the archives of a rated package, keeping the original `tarball` of packages from npm
+/// if configured to. `content_size` is the size of `content` once decoded.
+/// When `replacing` a package's archives, a zip kept beside the old ones is removed if there's
+/// no new one.
+async fn store_archives(
+    id: &PackageId,
+    content: Vec<u8>,
+    content_size: u64,
+    tarball: Option<Vec<u8>>,
+    replacing: bool,
+) -> Result<StoredArchives, ApiError> {
+    let storage = CloudStorage::new().await?;
+
+    let (main, size, format, zip) = match (tarball, NpmArchives::get()) {
+        (Some(tarball), NpmArchives::Tarball) => {
+            let size = tarball.len() as u64;
+            (tarball, size, ArchiveFormat::Tarball, None)
+        }
+        (Some(tarball), NpmArchives::Both) => {
+            let size = tarball.len() as u64;
+            (tarball, size, ArchiveFormat::Tarball, Some(content))
+        }
+        _ => (content, content_size, ArchiveFormat::Zip, None),
+    };
+    let url = storage.put_object(id.as_ref().to_owned(), main).await?;
+    let zip_url = match zip {
+        Some(zip) => Some(storage.put_object(zip_object(id), zip).await?),
+        None => {
+            if replacing {
+                // usually there was none, so failing here is expected
+                if let Err(e) = storage.delete_object(zip_object(id)).await {
+                    log::info!("no old zip of `{}` removed: {}", id.as_ref(), e);
+                }
+            }
+            None
+        }
+    };
+
+    Ok(StoredArchives {
+        url,
+        size,
+        format,
+        zip_url,
+    })
+}
+
+/// Rate a package and add it to the registry.
+/// When run as a job, its state is updated as ingestion goes along.
+pub async fn ingest(data: PackageData, job: Option<&JobId>) -> Result<Package, ApiError> {
     let db = database::get_database().await;
+    jobs::set_state(&db, job, JobState::Rating).await;
 
     let RatedPackage {
         name,
         version,
         id,
+        license,
+        dependencies,
         rating,
         content,
-    } = scoring::rate_package(data)
-        .await
-        .map_err(scoring_err_to_response)?;
+        content_size,
+        commit,
+        integrity,
+        tarball,
+    } = scoring::rate_package(data).await?;
 
-    if rating.net_score < MIN_ALLOWED_NET_SCORE {
-        return Err(StatusCode::FAILED_DEPENDENCY);
-    }
+    check_net_score(rating.net_score)?;
 
     let query = db
         .fluent()
@@ -187,89 +361,350 @@ pub async fn post_package(
         .limit(1)
         .filter(|q| q.field(database::NAME).eq(&name));
 
-    let query_result = query.query().await.map_err(|e| {
-        log::error!("{}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let query_result = query.query().await?;
 
     if query_result.len() >= 1 {
-        return Err(StatusCode::CONFLICT);
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("package `{}` already exists", name),
+        ));
     }
 
     // upload to obj storage
-    let storage = CloudStorage::new().await.map_err(|e| {
-        log::error!("cloud storage handle error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    let url = storage
-        .put_object(id.as_ref().to_owned(), content)
-        .await
-        .map_err(|e| {
-            log::error!("cloud storage put error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    jobs::set_state(&db, job, JobState::Uploading).await;
+    let StoredArchives {
+        url,
+        size,
+        format,
+        zip_url,
+    } = store_archives(&id, content, content_size, tarball, false).await?;
 
     let metadata = PackageMetadata { name, version, id };
 
     let entry = DatabaseEntry {
         metadata,
         url,
+        license,
+        size,
+        commit,
+        integrity,
+        format,
+        zip_url,
+        dependency_names: dependency_names(&dependencies),
+        dependencies,
+        job: job.cloned(),
         rating,
     };
 
+    jobs::set_state(&db, job, JobState::Storing).await;
     db.fluent()
         .insert()
         .into(database::METADATA)
         .document_id(&entry.metadata.id)
         .object(&entry)
-        .execute()
-        .await
-        .map_err(|e| {
-            log::error!("{}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .execute::<()>()
+        .await?;
 
-    // 201: return package
-    Ok(respond(StatusCode::CREATED, entry.into()))
+    let DatabaseEntry {
+        metadata,
+        url,
+        rating,
+        ..
+    } = entry;
+    record_rating(&db, metadata.id.clone(), rating).await;
+
+    Ok(Package {
+        metadata,
+        data: PackageData::Url {
+            url,
+            version: None,
+            reference: None,
+        },
+    })
 }
 
 pub async fn get_rating_by_id(
     Path(id): Path<PackageId>,
-) -> Result<MyResponse<PackageRating>, StatusCode> {
+) -> Result<MyResponse<PackageRating>, ApiError> {
     let db = database::get_database().await;
     Ok(ok(find_package_by_id(&db, id, RATING_FIELDS).await?))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CostQuery {
+    /// also add up the sizes of the package's dependencies
+    dependencies: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SizeAndDependencies {
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "Size", default)]
+    size: u64,
+    #[serde(rename = "Dependencies", default)]
+    dependencies: HashMap<String, String>,
+}
+
+const COST_FIELDS: [&str; 3] = [database::NAME, database::SIZE, database::DEPENDENCIES];
+
+/// Get the cost of adopting this package.
+///
+/// The size of the stored package, and optionally the total size of it and its dependencies.
+/// Dependencies are resolved like in [`get_dependencies_by_id`], to the newest package in the
+/// registry that satisfies them.
+pub async fn get_cost_by_id(
+    Path(id): Path<PackageId>,
+    Query(CostQuery { dependencies }): Query<CostQuery>,
+) -> Result<MyResponse<PackageCost>, ApiError> {
+    // 200: return cost
+    // 404: does not exist
+    let db = database::get_database().await;
+    let package: SizeAndDependencies = find_package_by_id(&db, id.clone(), COST_FIELDS).await?;
+
+    if !dependencies.unwrap_or(false) {
+        return Ok(ok(PackageCost {
+            id,
+            standalone_cost: package.size,
+            total_cost: None,
+            unresolved: None,
+        }));
+    }
+
+    // walk the dependency graph a level at a time, counting each package once even if several
+    // packages depend on it (or there is a cycle back to this one)
+    let mut seen = HashSet::from([package.name]);
+    let mut total_cost = package.size;
+    let mut unresolved = Vec::new();
+    let mut next: Vec<_> = package.dependencies.into_iter().collect();
+    while !next.is_empty() {
+        let level: Vec<_> = next
+            .drain(..)
+            .filter(|(name, _)| seen.insert(name.clone()))
+            .collect();
+        let found = futures::future::try_join_all(
+            level
+                .iter()
+                .map(|(name, requirement)| resolve_cost(&db, name, requirement)),
+        )
+        .await?;
+
+        for ((name, _), dependency) in level.into_iter().zip(found) {
+            match dependency {
+                Some(dependency) => {
+                    total_cost += dependency.size;
+                    next.extend(dependency.dependencies);
+                }
+                None => unresolved.push(name),
+            }
+        }
+    }
+    unresolved.sort();
+
+    Ok(ok(PackageCost {
+        id,
+        standalone_cost: package.size,
+        total_cost: Some(total_cost),
+        unresolved: Some(unresolved),
+    }))
+}
+
+/// Size and dependencies of the package a dependency resolves to, if it's in the registry
+async fn resolve_cost(
+    db: &FirestoreDb,
+    name: &str,
+    requirement: &str,
+) -> Result<Option<SizeAndDependencies>, ApiError> {
+    match resolve_dependency(db, name, requirement).await? {
+        Some(PackageMetadata { id, .. }) => {
+            Ok(Some(find_package_by_id(db, id, COST_FIELDS).await?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Find the newest package in the registry satisfying a dependency's version requirement
+async fn resolve_dependency(
+    db: &FirestoreDb,
+    name: &str,
+    requirement: &str,
+) -> Result<Option<PackageMetadata>, ApiError> {
+    let Some((name, requirements)) = scoring::registry_requirement(name, requirement) else {
+        // git, tarball and local dependencies don't come from the registry
+        return Ok(None);
+    };
+
+    let candidates: Vec<PackageMetadata> = db
+        .fluent()
+        .select()
+        .fields(PACKAGE_METADATA_FIELDS)
+        .from(database::METADATA)
+        .filter(|q| q.field(database::NAME).eq(name))
+        .obj()
+        .query()
+        .await?;
+
+    Ok(candidates
+        .into_iter()
+        .filter(|c| requirements.iter().any(|r| r.matches(&c.version)))
+        .max_by(|a, b| a.version.cmp(&b.version)))
+}
+
+/// Get the dependencies of this package.
+///
+/// Each dependency is resolved to the newest package in the registry that satisfies it.
+pub async fn get_dependencies_by_id(
+    Path(id): Path<PackageId>,
+) -> Result<MyResponse<Vec<Dependency>>, ApiError> {
+    // 200: return dependencies
+    // 404: does not exist
+    #[derive(Debug, Deserialize)]
+    struct JustDependencies {
+        #[serde(rename = "Dependencies", default)]
+        dependencies: HashMap<String, String>,
+    }
+
+    let db = database::get_database().await;
+    let JustDependencies { dependencies } =
+        find_package_by_id(&db, id, [database::DEPENDENCIES]).await?;
+
+    let mut dependencies: Vec<_> = dependencies.into_iter().collect();
+    dependencies.sort();
+    let resolved = futures::future::try_join_all(
+        dependencies
+            .iter()
+            .map(|(name, requirement)| resolve_dependency(&db, name, requirement)),
+    )
+    .await?;
+
+    Ok(ok(dependencies
+        .into_iter()
+        .zip(resolved)
+        .map(|((name, requirement), resolved)| Dependency {
+            name,
+            requirement,
+            resolved,
+        })
+        .collect()))
+}
+
+/// Get the packages in the registry that depend on this package.
+///
+/// Only packages whose version requirement is satisfied by this version are included.
+pub async fn get_dependents_by_id(
+    Path(id): Path<PackageId>,
+) -> Result<MyResponse<Vec<PackageMetadata>>, ApiError> {
+    // 200: return dependents
+    // 404: does not exist
+    #[derive(Debug, Deserialize)]
+    struct WithDependencies {
+        #[serde(flatten)]
+        metadata: PackageMetadata,
+        #[serde(rename = "Dependencies", default)]
+        dependencies: HashMap<String, String>,
+    }
+
+    let db = database::get_database().await;
+    let PackageMetadata { name, version, .. } =
+        find_package_by_id(&db, id, PACKAGE_METADATA_FIELDS).await?;
+
+    let candidates: Vec<WithDependencies> = db
+        .fluent()
+        .select()
+        .fields(
+            PACKAGE_METADATA_FIELDS
+                .iter()
+                .chain([&database::DEPENDENCIES]),
+        )
+        .from(database::METADATA)
+        .filter(|q| q.field(database::DEPENDENCY_NAMES).array_contains(&name))
+        .obj()
+        .query()
+        .await?;
+
+    let depends_on_this = |(dependency, spec): (&String, &String)| {
+        scoring::registry_requirement(dependency, spec).is_some_and(|(resolved, requirements)| {
+            resolved == name && requirements.iter().any(|r| r.matches(&version))
+        })
+    };
+    Ok(ok(candidates
+        .into_iter()
+        .filter(|c| c.dependencies.iter().any(depends_on_this))
+        .map(|c| c.metadata)
+        .collect()))
+}
+
+/// Get the history of this package's ratings, oldest first.
+pub async fn get_rating_history_by_id(
+    Path(id): Path<PackageId>,
+) -> Result<MyResponse<Vec<RatingRecord>>, ApiError> {
+    // 200: return rating history
+    // 404: does not exist
+    let db = database::get_database().await;
+    let PackageMetadata { id, .. } = find_package_by_id(&db, id, PACKAGE_METADATA_FIELDS).await?;
+
+    let mut history: Vec<HistoryEntry> = db
+        .fluent()
+        .select()
+        .from(database::RATING_HISTORY)
+        .filter(|q| q.field(database::ID).eq(&id))
+        .obj()
+        .query()
+        .await?;
+
+    // sorted here rather than by firestore, which would need an index on (ID, Timestamp)
+    history.sort_by_key(|entry| entry.timestamp);
+    Ok(ok(history.into_iter().map(RatingRecord::from).collect()))
+}
+
 /// Delete this version of the package.
 // not in baseline requirements
-pub async fn delete_package_by_id(Path(path_id): Path<PackageId>) -> Result<(), StatusCode> {
+pub async fn delete_package_by_id(Path(path_id): Path<PackageId>) -> Result<(), ApiError> {
     // 200: package deleted
     // 404: does not exist
     let db = database::get_database().await;
 
-    let PackageWithUrl {
-        metadata: PackageMetadata { id, .. },
-        ..
-    } = find_package_by_id(&db, path_id, PACKAGE_FIELDS).await?;
-
-    let storage = CloudStorage::new().await.map_err(|e| {
-        log::error!("while getting storage bucket: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    #[derive(Deserialize)]
+    struct StoredPackage {
+        #[serde(rename = "ID")]
+        id: PackageId,
+        #[serde(rename = "ZipURL", default)]
+        zip_url: Option<String>,
+    }
+    let StoredPackage { id, zip_url } =
+        find_package_by_id(&db, path_id, [database::ID, database::ZIP_URL]).await?;
+
+    let storage = CloudStorage::new().await?;
+    if zip_url.is_some() {
+        if let Err(e) = storage.delete_object(zip_object(&id)).await {
+            log::error!("deleting zip of `{}`: {}", id.as_ref(), e);
+        }
+    }
 
-    match join!(
+    let (object, metadata, history) = join!(
         storage.delete_object(id.as_ref().to_owned()),
         db.fluent()
             .delete()
             .from(database::METADATA)
-            .document_id(id)
-            .execute()
-            .map(|r| r.map_err(|e| {
-                log::error!("deleting metadata: {}", e);
-                ()
-            })),
-    ) {
-        (Ok(_), Ok(_)) => Ok(()),
-        _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+            .document_id(&id)
+            .execute(),
+        delete_rating_history(&db, &id),
+    );
+    object?;
+    metadata?;
+    history?;
+    Ok(())
+}
+
+/// Forget every rating recorded for a package
+async fn delete_rating_history(db: &FirestoreDb, id: &PackageId) -> firestore::FirestoreResult<()> {
+    let documents = db
+        .fluent()
+        .select()
+        .fields([database::ID])
+        .from(database::RATING_HISTORY)
+        .filter(|q| q.field(database::ID).eq(id))
+        .query()
+        .await?;
+    database::delete_documents(db, database::RATING_HISTORY, documents).await
 }
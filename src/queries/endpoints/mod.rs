@@ -1,82 +1,41 @@
 mod id;
 mod search;
-use firestore::{FirestoreStreamingBatchWriteOptions, FirestoreStreamingBatchWriter};
 pub use id::*;
 pub use search::*;
-use serde::Deserialize;
 use tokio::join;
 
-use super::{types::PackageId, *};
-use crate::{database, storage::CloudStorage, user::AuthenticationRequest};
-
-use axum::{
-    extract::{Json, Path},
-    http::StatusCode,
-    response::IntoResponse,
+use super::{
+    types::{CacheStats, Job},
+    *,
+};
+use crate::{
+    cache,
+    database::{self, JobEntry},
+    error::{ApiError, Json, Path},
+    rescore,
+    storage::CloudStorage,
+    user::AuthenticationRequest,
 };
 
-async fn clear_metadata() -> Result<(), StatusCode> {
-    #[derive(Deserialize, Debug)]
-    struct JustId {
-        #[serde(rename = "ID")]
-        id: PackageId,
-    }
+use axum::http::StatusCode;
 
-    // read all ids
+/// Delete every document in `collection`
+async fn clear_collection(collection: &str) -> Result<(), ApiError> {
     let db = database::get_database().await;
-    let all_ids: Vec<JustId> = db
+    let documents = db
         .fluent()
         .select()
         .fields([database::ID])
-        .from(database::METADATA)
-        .obj()
+        .from(collection)
         .query()
-        .await
-        .map_err(|e| {
-            log::error!("error reading IDs during delete: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    // create a batch writer
-    let (batch_stream, _) =
-        FirestoreStreamingBatchWriter::new(db.clone(), FirestoreStreamingBatchWriteOptions::new())
-            .await
-            .map_err(|e| {
-                log::error!("something went wrong creating a batch: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-    // add all delete operations to batch writer
-    let mut batch = batch_stream.new_batch();
-    for id in all_ids {
-        batch
-            .delete_by_id(database::METADATA, id.id, None)
-            .map_err(|e| {
-                log::error!("batch.delete_by_id() returned err: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-    }
-
-    // execute batch write
-    batch.write().await.map_err(|e| {
-        log::error!("while executing metadata deletions: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    // not sure if this is needed?
-    batch_stream.finish().await;
-
+        .await?;
+    database::delete_documents(&db, collection, documents).await?;
     Ok(())
 }
 
-async fn clear_bucket() -> Result<(), StatusCode> {
-    let storage = CloudStorage::new().await.map_err(|e| {
-        log::error!("while getting storage bucket: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    storage.delete_all().await.map_err(|_| {
-        log::error!("while executing bucket deletion");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+async fn clear_bucket() -> Result<(), ApiError> {
+    let storage = CloudStorage::new().await?;
+    storage.delete_all().await?;
     Ok(())
 }
 
@@ -84,45 +43,109 @@ async fn clear_bucket() -> Result<(), StatusCode> {
 ///
 /// Reset the registry to a system default state.
 // TODO: clear metadata
-pub async fn reset_registry() -> Result<StatusCode, StatusCode> {
+pub async fn reset_registry() -> Result<StatusCode, ApiError> {
     // 200: reset registry
-    match join!(clear_metadata(), clear_bucket()) {
-        (Err(_), _) | (_, Err(_)) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-        _ => Ok(StatusCode::OK),
+    // the bucket also holds the content uploaded for jobs
+    let (metadata, history, jobs, bucket) = join!(
+        clear_collection(database::METADATA),
+        clear_collection(database::RATING_HISTORY),
+        clear_collection(database::JOBS),
+        clear_bucket()
+    );
+    metadata?;
+    history?;
+    jobs?;
+    bucket?;
+    Ok(StatusCode::OK)
+}
+
+/// Re-score every package in the registry.
+///
+/// Runs in the background, updating each package's rating as it goes.
+pub async fn rescore_registry() -> Result<StatusCode, ApiError> {
+    // 202: started re-scoring
+    // 409: already re-scoring
+    if rescore::start() {
+        Ok(StatusCode::ACCEPTED)
+    } else {
+        Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "the registry is already being re-scored",
+        ))
     }
 }
 
+/// Get the response cache's hit and miss counts.
+pub async fn get_cache_stats() -> MyResponse<CacheStats> {
+    // 200: return counts
+    ok(cache::get().stats())
+}
+
+/// Empty the response cache, so that the next scoring queries GitHub and npm again.
+pub async fn purge_cache() -> MyResponse<CacheStats> {
+    // 200: return counts from before the purge
+    let stats = cache::get().stats();
+    let purged = cache::get().purge();
+    log::info!("purged {} cached responses", purged);
+    ok(stats)
+}
+
+/// Get the state of an ingestion job, and its result once it has finished.
+pub async fn get_job_by_id(Path(id): Path<String>) -> Result<MyResponse<Job>, ApiError> {
+    // 200: return job
+    // 404: does not exist
+    let db = database::get_database().await;
+    let entry: Option<JobEntry> = db
+        .fluent()
+        .select()
+        .by_id_in(database::JOBS)
+        .obj()
+        .one(&id)
+        .await?;
+
+    entry
+        .map(|entry| ok(entry.job))
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("no job with ID `{}`", id)))
+}
+
 /// Create an access token.
 // not in baseline requirements
-pub async fn authenticate(Json(_auth): Json<AuthenticationRequest>) -> impl IntoResponse {
+pub async fn authenticate(Json(_auth): Json<AuthenticationRequest>) -> Result<(), ApiError> {
     // 200: return token
     // 401: invalid user/password
     // 501: not implemented
-    StatusCode::NOT_IMPLEMENTED
+    Err(not_implemented("authentication"))
 }
 
 /// Return the history of this package (all versions).
 // not in baseline requirements
-pub async fn get_package_by_name(Path(_name): Path<String>) -> impl IntoResponse {
+pub async fn get_package_by_name(Path(_name): Path<String>) -> Result<(), ApiError> {
     // 200: return package history
     // 404: does not exist
-    StatusCode::NOT_IMPLEMENTED
+    Err(not_implemented("getting packages by name"))
 }
 
 /// Delete all versions of this package.
 // not in baseline requirements
-pub async fn delete_package_by_name(Path(_name): Path<String>) -> impl IntoResponse {
+pub async fn delete_package_by_name(Path(_name): Path<String>) -> Result<(), ApiError> {
     // 200: package deleted
     // 404: does not exist
-    StatusCode::NOT_IMPLEMENTED
+    Err(not_implemented("deleting packages by name"))
 }
 
 /// Get any packages fitting the regular expression.
 ///
 /// Search for a package using regular expression over package names and READMEs.
 // not in baseline requirements
-pub async fn get_package_by_regex(_regex: String) -> impl IntoResponse {
+pub async fn get_package_by_regex(_regex: String) -> Result<(), ApiError> {
     // 200: return list of packages
     // 404: no packages found
-    StatusCode::NOT_IMPLEMENTED
+    Err(not_implemented("searching by regular expression"))
+}
+
+fn not_implemented(what: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::NOT_IMPLEMENTED,
+        format!("{} is not implemented", what),
+    )
 }
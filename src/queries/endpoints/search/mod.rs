@@ -2,12 +2,12 @@
 mod tests;
 
 use super::{filter, ok, types::*, MyResponse};
-use crate::database;
-
-use axum::{
-    extract::{Json, Query},
-    http::{HeaderName, HeaderValue, StatusCode},
+use crate::{
+    database,
+    error::{ApiError, Json, Query},
 };
+
+use axum::http::{HeaderName, HeaderValue, StatusCode};
 use firestore::{FirestoreQueryCursor, FirestoreQueryDirection, FirestoreValue};
 use semver::VersionReq;
 use serde::Deserialize;
@@ -41,10 +41,10 @@ impl Offset {
 pub async fn search_packages(
     Query(Offset { offset }): Query<Offset>,
     Json(search): Json<Vec<SearchQuery>>,
-) -> Result<MyResponse<Vec<PackageMetadata>>, StatusCode> {
+) -> Result<MyResponse<Vec<PackageMetadata>>, ApiError> {
     // have to have packages to search for
     if search.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "no queries given"));
     }
 
     // hard to support this because of the firestore filtering / sorting rules
@@ -62,7 +62,10 @@ pub async fn search_packages(
     // Another possible solution is telling the client to do that themselves.
     // (the client knows when it's hit the last page because the offset header field isn't set)
     if search.len() > 1 {
-        return Err(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+        return Err(ApiError::new(
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            "only one query at a time is supported",
+        ));
     }
 
     // know at least one element from early return above
@@ -132,10 +135,7 @@ pub async fn search_packages(
         None => query,
     };
 
-    let result = query.obj().query().await.map_err(|e| {
-        log::error!("{}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let result: Vec<PackageMetadata> = query.obj().query().await?;
 
     // 200: list of packages
     Ok(if result.len() < database::PAGE_LIMIT {
@@ -3,6 +3,7 @@ mod tests;
 
 use crate::database;
 
+use chrono::{DateTime, Utc};
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
@@ -62,6 +63,12 @@ pub enum PackageData {
     Url {
         #[serde(rename = "URL")]
         url: String,
+        /// version or dist-tag to ingest, only for npm packages; overrides one given in the URL
+        #[serde(rename = "Version", default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        /// tag, branch or commit to ingest, only for GitHub repositories; overrides one in the URL
+        #[serde(rename = "Ref", default, skip_serializing_if = "Option::is_none")]
+        reference: Option<String>,
     },
 }
 
@@ -75,11 +82,34 @@ impl From<database::DatabaseEntry> for Package {
     fn from(database::DatabaseEntry { metadata, url, .. }: database::DatabaseEntry) -> Self {
         Package {
             metadata,
-            data: PackageData::Url { url },
+            data: PackageData::Url {
+                url,
+                version: None,
+                reference: None,
+            },
         }
     }
 }
 
+/// A package in the registry, with where to download it
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct StoredPackage {
+    pub metadata: PackageMetadata,
+    pub data: StoredPackageData,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StoredPackageData {
+    #[serde(rename = "URL")]
+    pub url: String,
+    /// format of the archive at `url`, which is only a zip for some packages from npm
+    #[serde(rename = "Format", default)]
+    pub format: ArchiveFormat,
+    /// a zip of the package kept as well, when `url` is its original tarball
+    #[serde(rename = "ZipURL", default, skip_serializing_if = "Option::is_none")]
+    pub zip_url: Option<String>,
+}
+
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct PackageWithUrl {
     #[serde(flatten)]
@@ -113,6 +143,33 @@ pub struct PackageRating {
     pub pull_request: f64,
     #[serde(rename = "NetScore")]
     pub net_score: f64,
+    /// dependencies whose license is not allowed by the license policy
+    #[serde(rename = "IncompatibleLicenses", default)]
+    pub incompatible_licenses: Vec<DependencyLicense>,
+    /// places that declare different licenses for the package
+    #[serde(rename = "LicenseConflicts", default)]
+    pub license_conflicts: Vec<LicenseConflict>,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DependencyLicense {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "License")]
+    pub license: String,
+}
+
+/// Two places that disagree about a package's license, such as its `package.json` and GitHub
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LicenseConflict {
+    #[serde(rename = "Source")]
+    pub source: String,
+    #[serde(rename = "License")]
+    pub license: String,
+    #[serde(rename = "OtherSource")]
+    pub other_source: String,
+    #[serde(rename = "OtherLicense")]
+    pub other_license: String,
 }
 
 impl PackageRating {
@@ -131,7 +188,135 @@ impl PackageRating {
     }
 }
 
-pub const RATING_FIELDS: [&str; 8] = [
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobId(String);
+
+impl JobId {
+    pub fn new() -> Self {
+        JobId(Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for JobId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Format of a stored package archive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ArchiveFormat {
+    /// extracted and re-packed, or uploaded as a zip
+    #[default]
+    #[serde(rename = "zip")]
+    Zip,
+    /// the gzipped tarball exactly as the npm registry serves it
+    #[serde(rename = "tgz")]
+    Tarball,
+}
+
+/// How far along a job is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum JobState {
+    Queued,
+    /// downloading, extracting and scoring the package
+    Rating,
+    /// uploading the package to storage
+    Uploading,
+    /// adding the package to the registry
+    Storing,
+    Succeeded,
+    Failed,
+}
+
+/// Why a job failed
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JobError {
+    /// the status the request would have gotten if it hadn't been run as a job
+    #[serde(rename = "Status")]
+    pub status: u16,
+    /// the kind of scoring error, if scoring is what failed
+    #[serde(rename = "Kind", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// seconds to wait before submitting again, if GitHub's rate limit ran out
+    #[serde(
+        rename = "RetryAfter",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub retry_after: Option<u64>,
+    #[serde(rename = "Message")]
+    pub message: String,
+}
+
+/// Ingestion of a package, run in the background
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Job {
+    #[serde(rename = "ID")]
+    pub id: JobId,
+    #[serde(rename = "State")]
+    pub state: JobState,
+    /// the package that was added, once the job succeeded
+    #[serde(rename = "Package", default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<Package>,
+    #[serde(rename = "Error", default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JobError>,
+}
+
+/// How well the response cache is doing
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    #[serde(rename = "Hits")]
+    pub hits: u64,
+    #[serde(rename = "Misses")]
+    pub misses: u64,
+    /// entries held in memory
+    #[serde(rename = "Entries")]
+    pub entries: usize,
+}
+
+/// A rating of a package as it was computed at some point in time
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RatingRecord {
+    #[serde(rename = "Timestamp")]
+    pub timestamp: DateTime<Utc>,
+    /// ratings computed by different versions of the scoring policy may not be comparable
+    #[serde(rename = "PolicyVersion")]
+    pub policy_version: u32,
+    #[serde(flatten)]
+    pub rating: PackageRating,
+}
+
+/// A dependency of a package, and the package in the registry that it resolves to
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Dependency {
+    #[serde(rename = "Name")]
+    pub name: String,
+    /// the version requirement from its `package.json`
+    #[serde(rename = "Requirement")]
+    pub requirement: String,
+    /// the newest version in the registry that satisfies the requirement, if any
+    #[serde(rename = "Resolved")]
+    pub resolved: Option<PackageMetadata>,
+}
+
+/// How many bytes adopting a package costs
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PackageCost {
+    #[serde(rename = "ID")]
+    pub id: PackageId,
+    /// size of the package itself
+    #[serde(rename = "StandaloneCost")]
+    pub standalone_cost: u64,
+    /// size of the package and every package it depends on, directly or not
+    #[serde(rename = "TotalCost", skip_serializing_if = "Option::is_none")]
+    pub total_cost: Option<u64>,
+    /// dependencies that aren't in the registry, so aren't part of the total
+    #[serde(rename = "Unresolved", skip_serializing_if = "Option::is_none")]
+    pub unresolved: Option<Vec<String>>,
+}
+
+pub const RATING_FIELDS: [&str; 10] = [
     database::NET_SCORE,
     database::BUS_FACTOR,
     database::CORRECTNESS,
@@ -140,4 +325,6 @@ pub const RATING_FIELDS: [&str; 8] = [
     database::LICENSE_SCORE,
     database::GOOD_PINNING_PRACTICE,
     database::PULL_REQUEST,
+    database::INCOMPATIBLE_LICENSES,
+    database::LICENSE_CONFLICTS,
 ];
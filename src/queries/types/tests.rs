@@ -67,7 +67,63 @@ fn des_ser_data_url() {
     assert_eq!(
         deserialized,
         PackageData::Url {
-            url: "https://example.com".to_string()
+            url: "https://example.com".to_string(),
+            version: None,
+            reference: None,
+        }
+    );
+
+    let serialized = serde_json::to_string(&deserialized).unwrap();
+    assert_eq!(serialized, data);
+}
+
+#[test]
+fn des_ser_stored_data() {
+    // stored before formats were recorded
+    let deserialized: StoredPackageData =
+        serde_json::from_str(r#"{"URL":"https://example.com/a"}"#).unwrap();
+    assert_eq!(deserialized.format, ArchiveFormat::Zip);
+    assert_eq!(
+        serde_json::to_string(&deserialized).unwrap(),
+        r#"{"URL":"https://example.com/a","Format":"zip"}"#
+    );
+
+    let data =
+        r#"{"URL":"https://example.com/a","Format":"tgz","ZipURL":"https://example.com/a.zip"}"#;
+    let deserialized: StoredPackageData = serde_json::from_str(data).unwrap();
+    assert_eq!(deserialized.format, ArchiveFormat::Tarball);
+    assert_eq!(serde_json::to_string(&deserialized).unwrap(), data);
+}
+
+#[test]
+fn des_ser_data_url_version() {
+    let data = r#"{"URL":"https://www.npmjs.com/package/lodash","Version":"next"}"#;
+
+    let deserialized: PackageData = serde_json::from_str(data).unwrap();
+    assert_eq!(
+        deserialized,
+        PackageData::Url {
+            url: "https://www.npmjs.com/package/lodash".to_string(),
+            version: Some("next".to_string()),
+            reference: None,
+        }
+    );
+
+    let serialized = serde_json::to_string(&deserialized).unwrap();
+    assert_eq!(serialized, data);
+}
+
+#[test]
+fn des_ser_data_url_ref() {
+    let data = r#"{"URL":"https://github.com/lodash/lodash","Ref":"4.17.21"}"#;
+
+    let deserialized: PackageData = serde_json::from_str(data).unwrap();
+    assert_eq!(
+        deserialized,
+        PackageData::Url {
+            url: "https://github.com/lodash/lodash".to_string(),
+            version: None,
+            reference: Some("4.17.21".to_string()),
         }
     );
 
@@ -99,7 +155,9 @@ fn data_one_field_null() {
     assert_eq!(
         deserialized,
         PackageData::Url {
-            url: "abc".to_string()
+            url: "abc".to_string(),
+            version: None,
+            reference: None,
         }
     );
 
@@ -116,3 +174,70 @@ fn data_both_fields_null() {
         panic!("Expected to not set any fields when all null");
     }
 }
+
+#[test]
+fn ser_rating_record() {
+    let record = RatingRecord {
+        timestamp: "2023-03-01T12:00:00Z".parse().unwrap(),
+        policy_version: 1,
+        rating: PackageRating {
+            bus_factor: 0.5,
+            correctness: 1.,
+            ramp_up: 0.5,
+            responsive_maintainer: 0.,
+            license_score: 1.,
+            good_pinning_practice: 0.,
+            pull_request: 0.5,
+            net_score: 0.5,
+            incompatible_licenses: vec![],
+            license_conflicts: vec![],
+        },
+    };
+
+    let serialized = serde_json::to_string(&record).unwrap();
+    assert_eq!(
+        serialized,
+        concat!(
+            r#"{"Timestamp":"2023-03-01T12:00:00Z","PolicyVersion":1,"BusFactor":0.5,"#,
+            r#""Correctness":1.0,"RampUp":0.5,"ResponsiveMaintainer":0.0,"LicenseScore":1.0,"#,
+            r#""GoodPinningPractice":0.0,"PullRequest":0.5,"NetScore":0.5,"#,
+            r#""IncompatibleLicenses":[],"LicenseConflicts":[]}"#
+        )
+    );
+}
+
+#[test]
+fn ser_failed_job() {
+    let job = Job {
+        id: JobId("1234".to_owned()),
+        state: JobState::Failed,
+        package: None,
+        error: Some(JobError {
+            status: 400,
+            kind: Some("MissingRepository".to_owned()),
+            retry_after: None,
+            message: "no repository".to_owned(),
+        }),
+    };
+
+    let serialized = serde_json::to_string(&job).unwrap();
+    assert_eq!(
+        serialized,
+        concat!(
+            r#"{"ID":"1234","State":"Failed","#,
+            r#""Error":{"Status":400,"Kind":"MissingRepository","Message":"no repository"}}"#
+        )
+    );
+}
+
+#[test]
+fn archive_format_names() {
+    assert_eq!(
+        serde_json::to_string(&ArchiveFormat::Zip).unwrap(),
+        r#""zip""#
+    );
+    assert_eq!(
+        serde_json::from_str::<ArchiveFormat>(r#""tgz""#).unwrap(),
+        ArchiveFormat::Tarball
+    );
+}
@@ -0,0 +1,160 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    database::{self, HistoryEntry},
+    queries::types::{PackageData, PackageMetadata, PACKAGE_METADATA_FIELDS, RATING_FIELDS},
+    scoring::{self, RatedPackage},
+    storage::CloudStorage,
+};
+
+use base64::Engine;
+use firestore::{FirestoreDb, FirestoreWritePrecondition};
+use once_cell::sync::OnceCell;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+/// Whether packages are being re-scored, so that only one run happens at a time
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// How often to re-score every package.
+/// Set in hours with `RESCORE_INTERVAL_HOURS`, never if it isn't set.
+fn get_interval() -> Option<Duration> {
+    static INTERVAL_HOURS: OnceCell<Option<u64>> = OnceCell::new();
+    INTERVAL_HOURS
+        .get_or_init(|| {
+            std::env::var("RESCORE_INTERVAL_HOURS")
+                .ok()
+                .and_then(|hours| hours.parse().ok())
+                .filter(|hours| *hours > 0)
+        })
+        .map(|hours| Duration::from_secs(hours * 60 * 60))
+}
+
+/// Re-score every package on the configured schedule
+pub fn schedule() {
+    let Some(interval) = get_interval() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        // the first tick is immediate, but the packages were just scored when they were uploaded
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if !start() {
+                log::warn!("skipping scheduled re-scoring, the last one is still running");
+            }
+        }
+    });
+}
+
+/// Clears `RUNNING` when dropped, so a run that panics doesn't block every later one
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Start re-scoring every package in the background, unless that is already happening
+pub fn start() -> bool {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    let running = Running;
+    tokio::spawn(async move {
+        let _running = running;
+        rescore_all().await;
+    });
+    true
+}
+
+async fn rescore_all() {
+    let db = database::get_database().await;
+    let storage = match CloudStorage::new().await {
+        Ok(storage) => storage,
+        Err(e) => return log::error!("re-scoring: while getting storage bucket: {}", e),
+    };
+
+    let packages: Vec<PackageMetadata> = match db
+        .fluent()
+        .select()
+        .fields(PACKAGE_METADATA_FIELDS)
+        .from(database::METADATA)
+        .obj()
+        .query()
+        .await
+    {
+        Ok(packages) => packages,
+        Err(e) => return log::error!("re-scoring: while reading packages: {}", e),
+    };
+
+    // one at a time, to stay within GitHub's rate limits
+    let mut failed = 0;
+    for metadata in &packages {
+        if let Err(e) = rescore(&db, &storage, metadata).await {
+            log::error!(
+                "re-scoring {} {} (`{}`): {}",
+                metadata.name,
+                metadata.version,
+                metadata.id.as_ref(),
+                e
+            );
+            failed += 1;
+        }
+    }
+    log::info!(
+        "re-scored {} packages, {} failed",
+        packages.len() - failed,
+        failed
+    );
+}
+
+async fn rescore(
+    db: &FirestoreDb,
+    storage: &CloudStorage,
+    metadata: &PackageMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let content = storage.get_object(metadata.id.as_ref().to_owned()).await?;
+    let RatedPackage {
+        name,
+        version,
+        rating,
+        ..
+    } = scoring::rate_package(stored_package_data(content)).await?;
+
+    if name != metadata.name || version != metadata.version {
+        return Err(format!("stored content is for {} {}", name, version).into());
+    }
+
+    // the package may have been deleted meanwhile, and mustn't come back as just its rating
+    db.fluent()
+        .update()
+        .fields(RATING_FIELDS)
+        .in_col(database::METADATA)
+        .precondition(FirestoreWritePrecondition::Exists(true))
+        .document_id(&metadata.id)
+        .object(&rating)
+        .execute::<()>()
+        .await?;
+
+    // only once the package is known to still be there
+    database::record_rating(db, &HistoryEntry::now(metadata.id.clone(), rating)).await?;
+
+    Ok(())
+}
+
+/// Packages uploaded as content are stored as the base64 they were uploaded as, while packages
+/// ingested from a URL are stored as a zip, or as the tarball npm served
+fn stored_package_data(content: Vec<u8>) -> PackageData {
+    let content = if content.starts_with(b"PK") || scoring::is_gzip(&content) {
+        base64::engine::general_purpose::STANDARD.encode(content)
+    } else {
+        String::from_utf8_lossy(&content).into_owned()
+    };
+    PackageData::Content { content }
+}
@@ -0,0 +1,45 @@
+use super::*;
+
+#[test]
+fn stored_zip_encoded() {
+    let zip = b"PK\x03\x04rest of the zip".to_vec();
+    assert_eq!(
+        stored_package_data(zip),
+        PackageData::Content {
+            content: "UEsDBHJlc3Qgb2YgdGhlIHppcA==".to_string()
+        }
+    );
+}
+
+#[test]
+fn stored_tarball_encoded() {
+    let tarball = b"\x1f\x8b\x08rest".to_vec();
+    assert_eq!(
+        stored_package_data(tarball),
+        PackageData::Content {
+            content: "H4sIcmVzdA==".to_string()
+        }
+    );
+}
+
+#[test]
+fn stored_base64_unchanged() {
+    let content = b"UEsDBHJlc3Qgb2YgdGhlIHppcA==".to_vec();
+    assert_eq!(
+        stored_package_data(content),
+        PackageData::Content {
+            content: "UEsDBHJlc3Qgb2YgdGhlIHppcA==".to_string()
+        }
+    );
+}
+
+#[test]
+fn running_cleared_after_panic() {
+    RUNNING.store(true, Ordering::SeqCst);
+    let result = std::panic::catch_unwind(|| {
+        let _running = Running;
+        panic!("re-scoring failed");
+    });
+    assert!(result.is_err());
+    assert!(!RUNNING.load(Ordering::SeqCst));
+}
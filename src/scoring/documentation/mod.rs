@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod tests;
+
+use std::{ffi::OsStr, fs, path::Path};
+use walkdir::{DirEntry, WalkDir};
+
+/// Directory names that hold prose documentation
+static DOCS_DIRS: [&str; 3] = ["docs", "doc", "documentation"];
+/// Directory names that hold runnable examples
+static EXAMPLES_DIRS: [&str; 4] = ["example", "examples", "demo", "demos"];
+/// Extensions of files whose comments are counted
+static SOURCE_EXTENSIONS: [&str; 7] = ["js", "mjs", "cjs", "jsx", "ts", "mts", "tsx"];
+/// JSDoc tags that show a comment documents an API
+static JSDOC_TAGS: [&str; 5] = ["@param", "@returns", "@return", "@type", "@typedef"];
+
+/// Source files larger than this are assumed to be generated and skipped
+const MAX_SOURCE_BYTES: u64 = 1 << 20;
+/// Fraction of source lines that are comments at which the comment density score is full
+const FULL_COMMENT_DENSITY: f64 = 0.2;
+
+/// What documentation ships with a package, found by looking through its contents
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct Documentation {
+    docs_dir: bool,
+    /// TypeScript declaration files or JSDoc annotations
+    api_docs: bool,
+    examples_dir: bool,
+    homepage: bool,
+    comment_lines: usize,
+    source_lines: usize,
+}
+
+impl Documentation {
+    /// Look through the files under `path`.
+    /// `homepage` is the `"homepage"` field of the package's `package.json`, if there is one.
+    pub(super) fn from_path<P: AsRef<Path>>(path: P, homepage: Option<&str>) -> Self {
+        let mut documentation = Documentation {
+            homepage: homepage.is_some_and(is_documentation_homepage),
+            ..Default::default()
+        };
+
+        let walker = WalkDir::new(path)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != "node_modules")
+            .filter_map(|e| e.ok());
+
+        for entry in walker {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if entry.file_type().is_dir() {
+                documentation.docs_dir |= DOCS_DIRS.contains(&name.as_str());
+                documentation.examples_dir |= EXAMPLES_DIRS.contains(&name.as_str());
+            } else if name.ends_with(".d.ts") {
+                documentation.api_docs = true;
+            } else if is_source(&entry, &name) {
+                let Ok(text) = fs::read(entry.path()) else {
+                    continue;
+                };
+                let comments = count_comments(&String::from_utf8_lossy(&text));
+                documentation.api_docs |= comments.jsdoc;
+                documentation.comment_lines += comments.comment_lines;
+                documentation.source_lines += comments.lines;
+            }
+        }
+
+        documentation
+    }
+
+    /// Score in [0, 1], graded by how many kinds of documentation were found
+    pub(super) fn score(&self) -> f64 {
+        let flag = |b: bool| if b { 1. } else { 0. };
+        let density = if self.source_lines == 0 {
+            0.
+        } else {
+            self.comment_lines as f64 / self.source_lines as f64
+        };
+        let comments = (density / FULL_COMMENT_DENSITY).min(1.);
+
+        (25. * flag(self.docs_dir)
+            + 20. * flag(self.api_docs)
+            + 15. * flag(self.examples_dir)
+            + 10. * flag(self.homepage)
+            + 30. * comments)
+            / 100.
+    }
+}
+
+/// A homepage that only links back to the repository's README is not extra documentation
+fn is_documentation_homepage(homepage: &str) -> bool {
+    let homepage = homepage.trim();
+    let readme_link = homepage.contains("github.com/") && homepage.ends_with("#readme");
+    !homepage.is_empty() && !readme_link
+}
+
+fn is_source(entry: &DirEntry, name: &str) -> bool {
+    let extension = entry.path().extension().and_then(OsStr::to_str);
+    extension.is_some_and(|e| SOURCE_EXTENSIONS.contains(&e))
+        && !name.contains(".min.")
+        && entry.metadata().is_ok_and(|m| m.len() <= MAX_SOURCE_BYTES)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CommentCount {
+    /// non-blank lines
+    lines: usize,
+    /// non-blank lines that are at least partly a comment
+    comment_lines: usize,
+    jsdoc: bool,
+}
+
+/// Count the lines of JavaScript/TypeScript source that contain a comment.
+///
+/// This is line based and doesn't understand strings, so `"/*"` in a string literal will be
+/// mistaken for the start of a comment. Good enough for an estimate.
+fn count_comments(text: &str) -> CommentCount {
+    let mut count = CommentCount::default();
+    let mut in_block = false;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        count.lines += 1;
+
+        let comment = if in_block {
+            Some(line)
+        } else {
+            line.find("//")
+                .into_iter()
+                .chain(line.find("/*"))
+                .min()
+                .map(|start| &line[start..])
+        };
+
+        let Some(comment) = comment else {
+            continue;
+        };
+        count.comment_lines += 1;
+        count.jsdoc |= JSDOC_TAGS.iter().any(|tag| comment.contains(tag));
+
+        // only track block comments that are opened (and not closed) on this line
+        if in_block || comment.starts_with("/*") {
+            let opened = comment.rfind("/*");
+            let closed = comment.rfind("*/");
+            in_block = match (opened, closed) {
+                (Some(o), Some(c)) => o > c,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => in_block,
+            };
+        }
+    }
+
+    count
+}
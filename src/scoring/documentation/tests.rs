@@ -0,0 +1,115 @@
+use super::*;
+
+use crate::queries::types::PackageId;
+
+#[test]
+fn comments_line_and_block() {
+    let text = r#"
+// a line comment
+const a = 1; // trailing
+
+/*
+ * a block
+ */
+function f() {
+    return a;
+}
+"#;
+    assert_eq!(
+        count_comments(text),
+        CommentCount {
+            lines: 8,
+            comment_lines: 5,
+            jsdoc: false,
+        }
+    );
+}
+
+#[test]
+fn comments_single_line_block() {
+    let text = "/* one line */\nconst a = 1;\n";
+    assert_eq!(
+        count_comments(text),
+        CommentCount {
+            lines: 2,
+            comment_lines: 1,
+            jsdoc: false,
+        }
+    );
+}
+
+#[test]
+fn comments_jsdoc() {
+    let text = r#"
+/**
+ * Add two numbers.
+ * @param {number} a
+ * @returns {number}
+ */
+function add(a, b) {}
+"#;
+    let count = count_comments(text);
+    assert!(count.jsdoc);
+    assert_eq!(count.comment_lines, 5);
+}
+
+#[test]
+fn homepage_readme_link() {
+    assert!(is_documentation_homepage("https://lodash.com/"));
+    assert!(!is_documentation_homepage(
+        "https://github.com/rtfeldman/node-test-runner#readme"
+    ));
+    assert!(!is_documentation_homepage(" "));
+}
+
+#[test]
+fn score_graded() {
+    assert_eq!(Documentation::default().score(), 0.);
+
+    let everything = Documentation {
+        docs_dir: true,
+        api_docs: true,
+        examples_dir: true,
+        homepage: true,
+        comment_lines: 1,
+        source_lines: 2,
+    };
+    assert_eq!(everything.score(), 1.);
+
+    let some = Documentation {
+        docs_dir: true,
+        comment_lines: 1,
+        source_lines: 10,
+        ..Default::default()
+    };
+    assert_eq!(some.score(), 0.4);
+}
+
+#[test]
+fn from_path_finds_documentation() {
+    let path = std::env::temp_dir().join(PackageId::new().as_ref());
+    fs::create_dir_all(path.join("package/docs")).unwrap();
+    fs::create_dir_all(path.join("package/node_modules/dep/examples")).unwrap();
+    fs::write(path.join("package/index.d.ts"), "export {};\n").unwrap();
+    fs::write(
+        path.join("package/index.js"),
+        "// hi\nmodule.exports = 1;\n",
+    )
+    .unwrap();
+
+    let documentation = Documentation::from_path(&path, Some("https://example.com"));
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(
+        documentation,
+        Documentation {
+            docs_dir: true,
+            api_docs: true,
+            // examples inside dependencies don't count
+            examples_dir: false,
+            homepage: true,
+            comment_lines: 1,
+            source_lines: 2,
+        }
+    );
+}
@@ -0,0 +1,431 @@
+#[cfg(test)]
+mod tests;
+
+use super::{
+    github::get_history_window,
+    host::{RepositoryHost, Snapshot},
+    license::{self, DeclaredLicense, LicenseSource},
+    readme::{self, Readme},
+    url::hosts_from_env,
+    CheckResults, RatingResult, ScoringData,
+};
+use crate::queries::types::PackageId;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    net::{IpAddr, ToSocketAddrs},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use zip::ZipArchive;
+
+const BITBUCKET_ORG: &str = "bitbucket.org";
+/// Seconds a git command may run before it is killed, unless `GIT_TIMEOUT` says otherwise
+const DEFAULT_TIMEOUT: u64 = 120;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    #[error("`git {command}` failed: {stderr}")]
+    Failed { command: String, stderr: String },
+    #[error("repository `{0}` does not exist or is private")]
+    NotFound(String),
+    #[error("`git {command}` took longer than {seconds}s")]
+    TimedOut { command: String, seconds: u64 },
+    #[error("`{0}` resolves to an internal address")]
+    InternalAddress(String),
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("{0}")]
+    JoinError(#[from] tokio::task::JoinError),
+}
+
+/// Whether repositories on the server's own file system, or served over plain `http://`, can be
+/// rated, set with `ALLOW_LOCAL_GIT`. Only meant for tests and local stand-ins.
+fn allow_local() -> bool {
+    static ALLOW: OnceCell<bool> = OnceCell::new();
+    *ALLOW.get_or_init(|| std::env::var("ALLOW_LOCAL_GIT").is_ok_and(|v| v == "1" || v == "true"))
+}
+
+/// Whether repositories on `host` can be cloned: bitbucket.org, or one of the comma separated
+/// hosts in `GIT_HOSTS`
+fn is_allowed_host(host: &str) -> bool {
+    static HOSTS: OnceCell<Vec<String>> = OnceCell::new();
+    HOSTS
+        .get_or_init(|| hosts_from_env("GIT_HOSTS", BITBUCKET_ORG))
+        .iter()
+        .any(|allowed| host.eq_ignore_ascii_case(allowed))
+}
+
+/// Addresses of the server itself or of its private network, which links mustn't lead to
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // shared address space, 100.64.0.0/10
+                || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64)
+        }
+        IpAddr::V6(ip) => {
+            ip.to_ipv4_mapped().is_some_and(|ip| is_internal(ip.into()))
+                || ip.is_loopback()
+                || ip.is_unspecified()
+                // unique local, fc00::/7
+                || ip.segments()[0] & 0xfe00 == 0xfc00
+                // link local, fe80::/10
+                || ip.segments()[0] & 0xffc0 == 0xfe80
+        }
+    }
+}
+
+/// The host a clone URL points at, like `bitbucket.org` for `git@bitbucket.org:user/repo.git`
+fn host_of(url: &str) -> Option<String> {
+    let host = match url.split_once("://") {
+        Some(_) => ::url::Url::parse(url).ok()?.host_str()?.to_owned(),
+        None => {
+            let (user_host, _) = url.split_once(':')?;
+            user_host.rsplit_once('@')?.1.to_owned()
+        }
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+/// Seconds a git command may run for, from `GIT_TIMEOUT`
+fn get_timeout() -> u64 {
+    static TIMEOUT: OnceCell<u64> = OnceCell::new();
+    *TIMEOUT.get_or_init(|| {
+        std::env::var("GIT_TIMEOUT")
+            .ok()
+            .and_then(|seconds| seconds.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT)
+    })
+}
+
+/// A repository on any host, reached with git itself rather than through an API
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct GitRepository {
+    /// what is passed to `git clone`
+    pub(super) url: String,
+}
+
+impl GitRepository {
+    /// A repository on Bitbucket, from the `bitbucket:user/repo` shorthand of `package.json`
+    pub(super) fn bitbucket(path: &str) -> Self {
+        GitRepository {
+            url: format!("https://{}/{}.git", BITBUCKET_ORG, path.trim_matches('/')),
+        }
+    }
+
+    fn is_local(&self) -> bool {
+        self.url.starts_with("file://")
+    }
+}
+
+impl RepositoryHost for GitRepository {
+    /// Links that can only be git repositories: ones using a git transport, or ending in `.git`,
+    /// on one of the allowed hosts
+    fn parse(url: &str) -> Option<Self> {
+        let url = url.trim_start_matches("git+");
+        let repository = GitRepository {
+            url: url.to_owned(),
+        };
+        if url.starts_with("file://") {
+            return allow_local().then_some(repository);
+        }
+
+        let git_transport = url.starts_with("ssh://") || url.starts_with("git://");
+        let web = url.starts_with("https://") && url.ends_with(".git");
+        let plain_http = url.starts_with("http://") && url.ends_with(".git") && allow_local();
+        // `git@bitbucket.org:user/repo.git`
+        let scp_like = !url.contains("://")
+            && url
+                .split_once(':')
+                .is_some_and(|(host, path)| host.contains('@') && !path.is_empty());
+        if !(git_transport || web || plain_http || scp_like) {
+            return None;
+        }
+
+        let host = host_of(url)?;
+        let internal = host.parse().is_ok_and(is_internal);
+        (is_allowed_host(&host) && (!internal || allow_local())).then_some(repository)
+    }
+
+    /// Also reads the history from the same clone, so the repository is rated at the commit
+    /// that was downloaded
+    async fn download(&self, path: &Path) -> RatingResult<Snapshot> {
+        let repository = self.clone();
+        let since = Utc::now() - get_history_window();
+        let (content, commit, history) = tokio::task::spawn_blocking(move || {
+            let clone = TempClone::new(&repository, since)?;
+            let commit = clone.git_text(&["rev-parse", "HEAD"])?;
+            Ok::<_, GitError>((
+                clone.git(&["archive", "--format=zip", "HEAD"])?,
+                commit.trim().to_owned(),
+                History::read(&clone, since)?,
+            ))
+        })
+        .await
+        .map_err(GitError::from)??;
+
+        ZipArchive::new(io::Cursor::new(&content[..]))?.extract(path)?;
+        Ok(Snapshot {
+            content,
+            commit: Some(commit),
+            metrics: Some(history.into()),
+        })
+    }
+
+    async fn metrics(&self) -> RatingResult<ScoringData> {
+        let repository = self.clone();
+        let since = Utc::now() - get_history_window();
+        let history = tokio::task::spawn_blocking(move || {
+            History::read(&TempClone::new(&repository, since)?, since)
+        })
+        .await
+        .map_err(GitError::from)??;
+
+        Ok(history.into())
+    }
+}
+
+/// Without an API there are no issues, pull requests or checks to look at, only the history
+impl From<History> for ScoringData {
+    fn from(history: History) -> Self {
+        ScoringData {
+            readme: history.readme,
+            // only known once the package contents have been looked through
+            documentation: None,
+            issues_closed: 0,
+            issues_total: 0,
+            checks: CheckResults::default(),
+            // only known once the package contents have been looked through
+            test_suite: None,
+            commit_counts: history.commit_counts,
+            response_times: None,
+            days_since_commit: history
+                .last_commit
+                .map(|at| (Utc::now() - at).num_seconds().max(0) as f64 / 86400.),
+            licenses: history.license.into_iter().collect(),
+            // only known once the package.json has been read
+            dependency_licenses: Vec::new(),
+        }
+    }
+}
+
+/// A clone in a temporary directory, removed when dropped
+struct TempClone {
+    dir: PathBuf,
+}
+
+impl TempClone {
+    /// Clone only the history since `since`, and only the files of the last commit, which is
+    /// what's downloaded and all the history that's rated
+    fn new(repository: &GitRepository, since: DateTime<Utc>) -> Result<Self, GitError> {
+        if !repository.is_local() {
+            check_addresses(&repository.url)?;
+        }
+
+        let clone = TempClone {
+            dir: std::env::temp_dir().join(PackageId::new().as_ref()),
+        };
+        let dir = clone.dir.to_string_lossy().into_owned();
+        let clone_with = |depth: &str| {
+            let result = run(
+                None,
+                &[
+                    "clone",
+                    "--quiet",
+                    "--filter=blob:none",
+                    depth,
+                    // so a URL can't be taken for an option
+                    "--",
+                    &repository.url,
+                    &dir,
+                ],
+            );
+            match result {
+                Err(GitError::Failed { stderr, .. }) if is_not_found(&stderr) => {
+                    Err(GitError::NotFound(repository.url.clone()))
+                }
+                result => result,
+            }
+        };
+        // there is no history to shallow to if nothing was committed since
+        match clone_with(&format!("--shallow-since={}", since.timestamp())) {
+            Err(GitError::Failed { .. }) => {
+                let _ = std::fs::remove_dir_all(&clone.dir);
+                clone_with("--depth=1")?
+            }
+            result => result?,
+        };
+        Ok(clone)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<Vec<u8>, GitError> {
+        run(Some(&self.dir), args)
+    }
+
+    fn git_text(&self, args: &[&str]) -> Result<String, GitError> {
+        Ok(String::from_utf8_lossy(&self.git(args)?).into_owned())
+    }
+}
+
+impl Drop for TempClone {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            log::error!("removing clone {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+/// Whether `git clone` failed because there's no repository at the URL, or none we can see
+fn is_not_found(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    stderr.contains("not found")
+        || stderr.contains("does not appear to be a git repository")
+        || stderr.contains("does not exist")
+}
+
+/// Make sure the host of a clone URL doesn't resolve to the server or its private network
+fn check_addresses(url: &str) -> Result<(), GitError> {
+    let host = host_of(url).ok_or_else(|| GitError::InternalAddress(url.to_owned()))?;
+    let internal = (host.as_str(), 0)
+        .to_socket_addrs()?
+        .any(|address| is_internal(address.ip()));
+    if internal && !allow_local() {
+        return Err(GitError::InternalAddress(host));
+    }
+    Ok(())
+}
+
+/// Read all of a pipe on another thread, so the child never blocks writing to it
+fn read_all(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+fn run(dir: Option<&Path>, args: &[&str]) -> Result<Vec<u8>, GitError> {
+    run_for(dir, args, Duration::from_secs(get_timeout()))
+}
+
+/// Run git, killing it if it takes longer than `timeout`
+fn run_for(dir: Option<&Path>, args: &[&str], timeout: Duration) -> Result<Vec<u8>, GitError> {
+    let name = args.first().copied().unwrap_or_default().to_owned();
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    // never wait for credentials or host keys that nobody is there to type in or accept
+    let mut child = command
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(GitError::TimedOut {
+                command: name,
+                seconds: timeout.as_secs(),
+            });
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    if status.success() {
+        Ok(stdout)
+    } else {
+        Err(GitError::Failed {
+            command: name,
+            stderr: String::from_utf8_lossy(&stderr.join().unwrap_or_default())
+                .trim()
+                .to_owned(),
+        })
+    }
+}
+
+/// What the history of the default branch says about a repository
+#[derive(Debug, Default, PartialEq)]
+struct History {
+    commit_counts: Vec<usize>,
+    last_commit: Option<DateTime<Utc>>,
+    readme: Option<Readme>,
+    license: Option<DeclaredLicense>,
+}
+
+impl History {
+    fn read(clone: &TempClone, since: DateTime<Utc>) -> Result<Self, GitError> {
+        let authors = clone.git_text(&[
+            "log",
+            &format!("--since={}", since.to_rfc3339()),
+            "--format=%ae",
+            "HEAD",
+        ])?;
+        let last_commit = clone.git_text(&["log", "-1", "--format=%cI", "HEAD"])?;
+        let files = clone.git_text(&["ls-tree", "--name-only", "HEAD"])?;
+        let show = |name: &str| {
+            clone
+                .git(&["show", &format!("HEAD:{}", name)])
+                .map(|text| String::from_utf8_lossy(&text).into_owned())
+                .ok()
+        };
+
+        let readme = files
+            .lines()
+            .filter(|name| readme::is_readme(name))
+            .find_map(show)
+            .map(|text| Readme::new(&text));
+        let license = files
+            .lines()
+            .filter(|name| license::is_license_file(name))
+            .filter_map(show)
+            .find_map(|text| license::identify_license_text(&text))
+            .map(|expression| DeclaredLicense {
+                source: LicenseSource::Repository,
+                expression,
+            });
+
+        Ok(History {
+            commit_counts: count_authors(&authors),
+            last_commit: DateTime::parse_from_rfc3339(last_commit.trim())
+                .ok()
+                .map(Into::into),
+            readme,
+            license,
+        })
+    }
+}
+
+/// Count commits per author email, one email per line
+fn count_authors(emails: &str) -> Vec<usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for email in emails.lines().map(str::trim).filter(|e| !e.is_empty()) {
+        *counts.entry(email.to_lowercase()).or_default() += 1;
+    }
+    counts.into_values().collect()
+}
@@ -0,0 +1,186 @@
+use super::*;
+
+use std::fs;
+
+/// Make a repository with commits by two authors
+fn make_repository() -> PathBuf {
+    let dir = std::env::temp_dir().join(PackageId::new().as_ref());
+    fs::create_dir_all(&dir).unwrap();
+    run(Some(&dir), &["init", "--quiet"]).unwrap();
+
+    let commit = |email: &str, file: &str, text: &str| {
+        fs::write(dir.join(file), text).unwrap();
+        run(Some(&dir), &["add", file]).unwrap();
+        let author = format!("user.email={}", email);
+        run(
+            Some(&dir),
+            &[
+                "-c",
+                &author,
+                "-c",
+                "user.name=Someone",
+                "commit",
+                "--quiet",
+                "-m",
+                file,
+            ],
+        )
+        .unwrap();
+    };
+    commit(
+        "ann@example.org",
+        "README.md",
+        "# thing\n\n## Installation\n\nnpm install thing\n",
+    );
+    commit(
+        "bob@example.org",
+        "LICENSE",
+        "MIT License\n\nPermission is hereby granted, free of charge, to any person\n",
+    );
+    commit("Ann@example.org", "index.js", "module.exports = 1;\n");
+
+    dir
+}
+
+#[test]
+fn parse_urls() {
+    let parse = |url: &str| GitRepository::parse(url).map(|r| r.url);
+    assert_eq!(
+        parse("git+ssh://git@bitbucket.org/abc/def.git").as_deref(),
+        Some("ssh://git@bitbucket.org/abc/def.git")
+    );
+    assert_eq!(
+        parse("git@bitbucket.org:abc/def.git").as_deref(),
+        Some("git@bitbucket.org:abc/def.git")
+    );
+    assert_eq!(
+        parse("https://bitbucket.org/abc/def.git").as_deref(),
+        Some("https://bitbucket.org/abc/def.git")
+    );
+
+    assert_eq!(parse("https://bitbucket.org/abc/def"), None);
+    assert_eq!(parse("github:abc/def"), None);
+    // GIT_HOSTS and ALLOW_LOCAL_GIT aren't set in tests
+    assert_eq!(parse("https://git.example.org/abc/def.git"), None);
+    assert_eq!(parse("ssh://git@127.0.0.1/abc/def.git"), None);
+    assert_eq!(parse("http://bitbucket.org/abc/def.git"), None);
+    assert_eq!(parse("file:///tmp/abc"), None);
+}
+
+#[test]
+fn hosts_of_urls() {
+    assert_eq!(
+        host_of("git@Bitbucket.org:abc/def.git").as_deref(),
+        Some("bitbucket.org")
+    );
+    assert_eq!(
+        host_of("ssh://git@bitbucket.org:22/abc/def.git").as_deref(),
+        Some("bitbucket.org")
+    );
+    assert_eq!(host_of("git://[::1]/abc.git").as_deref(), Some("::1"));
+    assert_eq!(host_of("abc/def"), None);
+}
+
+#[test]
+fn internal_addresses() {
+    let internal = |ip: &str| is_internal(ip.parse().unwrap());
+    assert!(internal("127.0.0.1"));
+    assert!(internal("10.1.2.3"));
+    assert!(internal("192.168.0.1"));
+    assert!(internal("169.254.169.254"));
+    assert!(internal("100.64.0.1"));
+    assert!(internal("::1"));
+    assert!(internal("fd00::1"));
+    assert!(internal("fe80::1"));
+    assert!(internal("::ffff:127.0.0.1"));
+
+    assert!(!internal("104.192.141.1"));
+    assert!(!internal("2600:1f18::1"));
+}
+
+#[test]
+fn commands_time_out() {
+    let started = Instant::now();
+    let result = run_for(
+        None,
+        &["-c", "alias.nap=!sleep 5", "nap"],
+        Duration::from_millis(200),
+    );
+    assert!(matches!(result, Err(GitError::TimedOut { .. })));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn authors_counted_by_email() {
+    let mut counts = count_authors("a@x.org\nb@x.org\nA@x.org\n\n");
+    counts.sort();
+    assert_eq!(counts, vec![1, 2]);
+}
+
+#[test]
+fn history_of_local_repository() {
+    let dir = make_repository();
+    let repository = GitRepository {
+        url: format!("file://{}", dir.display()),
+    };
+    let since = Utc::now() - chrono::Duration::days(1);
+    let history = TempClone::new(&repository, since).and_then(|clone| History::read(&clone, since));
+    fs::remove_dir_all(&dir).unwrap();
+    let mut history = history.unwrap();
+
+    history.commit_counts.sort();
+    assert_eq!(history.commit_counts, vec![1, 2]);
+    assert!(history.last_commit.is_some_and(|at| at <= Utc::now()));
+    assert!(history.readme.is_some_and(|r| r.readability() > 0.));
+    assert_eq!(
+        history.license,
+        Some(DeclaredLicense {
+            source: LicenseSource::Repository,
+            expression: "MIT".to_owned(),
+        })
+    );
+}
+
+#[tokio::test]
+async fn download_local_repository() {
+    let dir = make_repository();
+    let repository = GitRepository {
+        url: format!("file://{}", dir.display()),
+    };
+    let target = std::env::temp_dir().join(PackageId::new().as_ref());
+    let snapshot = repository.download(&target).await;
+    let head = run(Some(&dir), &["rev-parse", "HEAD"]).unwrap();
+    let index = fs::read_to_string(target.join("index.js"));
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&target).unwrap();
+
+    let snapshot = snapshot.unwrap();
+    assert!(!snapshot.content.is_empty());
+    // read from the same clone
+    assert!(snapshot
+        .metrics
+        .is_some_and(|metrics| metrics.commit_counts.len() == 2));
+    assert_eq!(
+        snapshot.commit.as_deref(),
+        Some(String::from_utf8_lossy(&head).trim())
+    );
+    assert_eq!(index.unwrap(), "module.exports = 1;\n");
+}
+
+#[test]
+fn missing_repository_not_found() {
+    let repository = GitRepository {
+        url: format!(
+            "file://{}",
+            std::env::temp_dir()
+                .join(PackageId::new().as_ref())
+                .display()
+        ),
+    };
+    let result = TempClone::new(&repository, Utc::now());
+    assert!(
+        matches!(&result, Err(GitError::NotFound(url)) if *url == repository.url),
+        "{:?}",
+        result.err()
+    );
+}
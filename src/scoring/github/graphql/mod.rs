@@ -0,0 +1,472 @@
+#[cfg(test)]
+mod tests;
+
+use super::{
+    super::upstream, cache_key, datetime::DateTime, rate_limit, readme, CheckResults,
+    DeclaredLicense, GithubUrl, GraphQlError, LicenseSource, Readme, ResponseTimes, ScoringData,
+};
+
+use crate::cache;
+
+use chrono::Utc;
+use graphql_client::GraphQLQuery;
+use std::collections::HashMap;
+
+/// Most pages of commit history to request when counting commit authors
+const MAX_HISTORY_PAGES: usize = 10;
+
+/// `GitTimestamp` scalar in the GitHub schema, an ISO-8601 encoded date string
+type GitTimestamp = String;
+
+#[allow(dead_code)]
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/scoring/github/schema.json",
+    query_path = "src/scoring/github/query.graphql",
+    response_derives = "Debug"
+)]
+pub struct GithubQuery;
+
+#[allow(dead_code)]
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/scoring/github/schema.json",
+    query_path = "src/scoring/github/history.graphql",
+    response_derives = "Debug"
+)]
+pub struct HistoryQuery;
+
+pub(in crate::scoring) async fn query(url: GithubUrl) -> Result<ScoringData, GraphQlError> {
+    let commit_counts = commit_counts(&url).await?;
+
+    let key = cache_key(&url);
+    let scoring_data: ScoringData = post::<GithubQuery>(url.into(), key)
+        .await?
+        .try_into()
+        .map_err(|_| GraphQlError::MissingData)?;
+
+    Ok(ScoringData {
+        commit_counts,
+        ..scoring_data
+    })
+}
+
+/// Send a query, or use the response cached under `key`
+async fn post<Q: GraphQLQuery>(
+    vars: Q::Variables,
+    key: String,
+) -> Result<graphql_client::Response<Q::ResponseData>, GraphQlError> {
+    if let Some(text) = cache::get().get(&key) {
+        return Ok(serde_json::from_str(&text)?);
+    }
+
+    let client = super::get_client();
+
+    let body = Q::build_query(vars);
+    let request = client
+        .post(upstream::get().github_graphql())
+        .bearer_auth(super::get_token())
+        .json(&body);
+    let response = rate_limit::send(rate_limit::Api::GraphQl, request).await?;
+
+    log::debug!("resp: {:?}", response);
+
+    let ok = response.status().is_success();
+    let text = response.text().await?;
+    rate_limit::observe_graphql(serde_json::from_str(&text)?)?;
+    let response: graphql_client::Response<Q::ResponseData> = serde_json::from_str(&text)?;
+    // errors might not happen next time, so only keep complete responses
+    if ok && response.data.is_some() && response.errors.is_none() {
+        cache::get().insert(&key, text);
+    }
+    Ok(response)
+}
+
+/// Count the commits each author made to the revision within the history window,
+/// following the history connection one page at a time
+async fn commit_counts(url: &GithubUrl) -> Result<Vec<usize>, GraphQlError> {
+    let since = (DateTime::now() - super::get_history_window()).to_rfc3339();
+
+    let mut counts = HashMap::new();
+    let mut after = None;
+    for page in 0..MAX_HISTORY_PAGES {
+        let vars = history_query::Variables {
+            owner: url.owner.clone(),
+            name: url.name.clone(),
+            revision: url.revision().to_owned(),
+            since: since.clone(),
+            after,
+        };
+        let key = format!("{}/history/{}", cache_key(url), page);
+        let page = post::<HistoryQuery>(vars, key)
+            .await?
+            .data
+            .ok_or(GraphQlError::MissingData)?;
+
+        after = count_authors(&mut counts, page).map_err(|_| GraphQlError::MissingData)?;
+        if after.is_none() {
+            break;
+        }
+    }
+
+    Ok(counts.into_values().collect())
+}
+
+/// Add the authors of one page of commit history to `counts`.
+/// Returns the cursor for the next page, if there is one.
+fn count_authors(
+    counts: &mut HashMap<String, usize>,
+    page: history_query::ResponseData,
+) -> Result<Option<String>, ()> {
+    use history_query::HistoryQueryRepositoryHead as Target;
+
+    let target = page.repository.ok_or(())?.head;
+    // an empty repository has no default branch, so no commits
+    let Some(Target::Commit(commit)) = target else {
+        return Ok(None);
+    };
+
+    for author in commit.history.nodes.into_iter().flatten().flatten() {
+        // the same person can commit with several emails, so prefer their account
+        let key = author
+            .author
+            .and_then(|a| a.user.map(|u| u.login).or(a.email).or(a.name));
+        *counts.entry(key.unwrap_or_default()).or_default() += 1;
+    }
+
+    let page_info = commit.history.page_info;
+    Ok(page_info
+        .has_next_page
+        .then_some(page_info.end_cursor)
+        .flatten())
+}
+
+impl From<GithubUrl> for <GithubQuery as GraphQLQuery>::Variables {
+    fn from(url: GithubUrl) -> Self {
+        // a file at the revision, as a git object expression
+        let file = |name: &str| format!("{}:{}", url.revision(), name);
+        Self {
+            readme_md: file("README.md"),
+            readme_lower_md: file("readme.md"),
+            readme_title_md: file("Readme.md"),
+            readme_plain: file("README"),
+            readme_markdown: file("README.markdown"),
+            readme_lower_markdown: file("readme.markdown"),
+            readme_rst: file("README.rst"),
+            readme_txt: file("README.txt"),
+            revision: url.revision().to_owned(),
+            name: url.name,
+            owner: url.owner,
+        }
+    }
+}
+
+impl<T> TryFrom<graphql_client::Response<T>> for ScoringData
+where
+    ScoringData: TryFrom<T, Error = ()>,
+{
+    type Error = ();
+    fn try_from(value: graphql_client::Response<T>) -> Result<Self, Self::Error> {
+        value.data.ok_or(())?.try_into()
+    }
+}
+
+impl TryFrom<github_query::ResponseData> for ScoringData {
+    type Error = ();
+    fn try_from(value: github_query::ResponseData) -> Result<Self, Self::Error> {
+        scoring_data_at(value, DateTime::now())
+    }
+}
+
+/// Build the scoring data from a response, treating `now` as the current time when measuring
+/// how long unanswered or unclosed issues have been waiting
+fn scoring_data_at(
+    value: github_query::ResponseData,
+    now: chrono::DateTime<Utc>,
+) -> Result<ScoringData, ()> {
+    let github_query::GithubQueryRepository {
+        issues_open,
+        issues_closed,
+        recent_issues,
+        recent_pull_requests,
+        head,
+        readme_md,
+        readme_lower_md,
+        readme_title_md,
+        readme_plain,
+        readme_markdown,
+        readme_lower_markdown,
+        readme_rst,
+        readme_txt,
+        license_info,
+    } = value.repository.ok_or(())?;
+
+    // each lookup has a type of its own, though all are of a blob's text
+    macro_rules! text {
+        ($name:literal, $object:expr, $kind:ident) => {
+            (
+                $name,
+                match $object {
+                    Some(github_query::$kind::Blob(github_query::BlobText { text })) => text,
+                    _ => None,
+                },
+            )
+        };
+    }
+    let readme_texts = [
+        text!("README.md", readme_md, GithubQueryRepositoryReadmeMd),
+        text!(
+            "readme.md",
+            readme_lower_md,
+            GithubQueryRepositoryReadmeLowerMd
+        ),
+        text!(
+            "Readme.md",
+            readme_title_md,
+            GithubQueryRepositoryReadmeTitleMd
+        ),
+        text!("README", readme_plain, GithubQueryRepositoryReadmePlain),
+        text!(
+            "README.markdown",
+            readme_markdown,
+            GithubQueryRepositoryReadmeMarkdown
+        ),
+        text!(
+            "readme.markdown",
+            readme_lower_markdown,
+            GithubQueryRepositoryReadmeLowerMarkdown
+        ),
+        text!("README.rst", readme_rst, GithubQueryRepositoryReadmeRst),
+        text!("README.txt", readme_txt, GithubQueryRepositoryReadmeTxt),
+    ];
+
+    let head = head.and_then(|target| match target {
+        github_query::GithubQueryRepositoryHead::Commit(commit) => Some(commit),
+        _ => None,
+    });
+    let readme = head
+        .as_ref()
+        .and_then(|commit| find_readme(commit, &readme_texts));
+    let checks = head.as_ref().map(check_results).unwrap_or_default();
+    let issues_closed = issues_closed.total_count.max(0) as usize;
+    let issues_open = issues_open.total_count.max(0) as usize;
+
+    let threads: Vec<_> = recent_issues
+        .nodes
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(Thread::from)
+        .chain(
+            recent_pull_requests
+                .nodes
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(Thread::from),
+        )
+        .collect();
+    let response_times = response_times(&threads, now);
+
+    // pseudo licenses are GitHub's "Other" and "No license", which are not SPDX identifiers
+    let licenses = license_info
+        .filter(|l| !l.pseudo_license)
+        .and_then(|l| l.spdx_id)
+        .map(|expression| DeclaredLicense {
+            source: LicenseSource::Github,
+            expression,
+        })
+        .into_iter()
+        .collect();
+
+    Ok(ScoringData {
+        readme,
+        // only known once the package contents have been looked through
+        documentation: None,
+        issues_closed,
+        issues_total: issues_closed + issues_open,
+        checks,
+        // only known once the package contents have been looked through
+        test_suite: None,
+        // requested separately, one page at a time
+        commit_counts: Vec::new(),
+        response_times,
+        // the default branch's last commit isn't needed when there are issues to go by
+        days_since_commit: None,
+        licenses,
+        // only known once the package.json has been read
+        dependency_licenses: Vec::new(),
+    })
+}
+
+/// Look through the files at the root of the revision for a README, whose text is among those
+/// looked up by name
+fn find_readme(
+    commit: &github_query::GithubQueryRepositoryHeadOnCommit,
+    texts: &[(&str, Option<String>)],
+) -> Option<Readme> {
+    commit
+        .tree
+        .entries
+        .iter()
+        .flatten()
+        .filter(|entry| entry.type_ == "blob" && readme::is_readme(&entry.name))
+        .find_map(|entry| {
+            texts
+                .iter()
+                .find(|(name, _)| *name == entry.name)
+                .and_then(|(_, text)| text.as_deref())
+        })
+        .map(Readme::new)
+}
+
+/// Tally the check suites that have finished running on the revision's commit
+fn check_results(commit: &github_query::GithubQueryRepositoryHeadOnCommit) -> CheckResults {
+    use github_query::{CheckConclusionState::*, CheckStatusState};
+
+    commit
+        .check_suites
+        .iter()
+        .flat_map(|suites| suites.nodes.iter().flatten().flatten())
+        .filter(|suite| matches!(suite.status, CheckStatusState::COMPLETED))
+        .fold(CheckResults::default(), |results, suite| {
+            match suite.conclusion {
+                Some(SUCCESS | NEUTRAL | SKIPPED) => CheckResults {
+                    passed: results.passed + 1,
+                    ..results
+                },
+                Some(FAILURE | TIMED_OUT | STARTUP_FAILURE | ACTION_REQUIRED | CANCELLED) => {
+                    CheckResults {
+                        failed: results.failed + 1,
+                        ..results
+                    }
+                }
+                // stale suites were never finished, and unknown conclusions can't be judged
+                _ => results,
+            }
+        })
+}
+
+/// An issue or pull request, reduced to the timestamps that show how quickly maintainers react
+#[derive(Debug, PartialEq)]
+pub(in crate::scoring) struct Thread {
+    pub(in crate::scoring) created_at: chrono::DateTime<Utc>,
+    pub(in crate::scoring) closed_at: Option<chrono::DateTime<Utc>>,
+    /// earliest comment or review from someone other than the author
+    pub(in crate::scoring) first_response: Option<chrono::DateTime<Utc>>,
+}
+
+/// Find the earliest reply that was not written by the author of the thread.
+/// Replies from deleted accounts (no author) still count as a response.
+fn first_response<'a, I>(author: Option<&str>, replies: I) -> Option<chrono::DateTime<Utc>>
+where
+    I: IntoIterator<Item = (&'a DateTime, Option<&'a str>)>,
+{
+    replies
+        .into_iter()
+        .filter(|(_, replier)| author.is_none() || *replier != author)
+        .map(|(created_at, _)| **created_at)
+        .min()
+}
+
+impl From<github_query::GithubQueryRepositoryRecentIssuesNodes> for Thread {
+    fn from(issue: github_query::GithubQueryRepositoryRecentIssuesNodes) -> Self {
+        let author = issue.author.map(|a| a.login);
+        let comments: Vec<_> = issue
+            .comments
+            .nodes
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect();
+        Thread {
+            created_at: *issue.created_at,
+            closed_at: issue.closed_at.map(|c| *c),
+            first_response: first_response(
+                author.as_deref(),
+                comments
+                    .iter()
+                    .map(|c| (&c.created_at, c.author.as_ref().map(|a| a.login.as_str()))),
+            ),
+        }
+    }
+}
+
+impl From<github_query::GithubQueryRepositoryRecentPullRequestsNodes> for Thread {
+    fn from(pull: github_query::GithubQueryRepositoryRecentPullRequestsNodes) -> Self {
+        let author = pull.author.map(|a| a.login);
+        let comments: Vec<_> = pull
+            .comments
+            .nodes
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect();
+        let reviews: Vec<_> = pull
+            .reviews
+            .and_then(|r| r.nodes)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect();
+        Thread {
+            created_at: *pull.created_at,
+            closed_at: pull.closed_at.map(|c| *c),
+            first_response: first_response(
+                author.as_deref(),
+                comments
+                    .iter()
+                    .map(|c| (&c.created_at, c.author.as_ref().map(|a| a.login.as_str())))
+                    .chain(
+                        reviews
+                            .iter()
+                            .map(|r| (&r.created_at, r.author.as_ref().map(|a| a.login.as_str()))),
+                    ),
+            ),
+        }
+    }
+}
+
+/// Median days until first response and until close over the given threads.
+///
+/// Threads still waiting on a response (or still open) count as having waited until `now`, so a
+/// backlog of ignored issues drags the medians up instead of being left out.
+pub(in crate::scoring) fn response_times(
+    threads: &[Thread],
+    now: chrono::DateTime<Utc>,
+) -> Option<ResponseTimes> {
+    let days = |end: chrono::DateTime<Utc>, start: chrono::DateTime<Utc>| {
+        (end.signed_duration_since(start).num_seconds() as f64 / 86400.).max(0.)
+    };
+
+    let first_response = threads
+        .iter()
+        .map(|t| {
+            let answered = t.first_response.into_iter().chain(t.closed_at).min();
+            days(answered.unwrap_or(now), t.created_at)
+        })
+        .collect();
+    let close = threads
+        .iter()
+        .map(|t| days(t.closed_at.unwrap_or(now), t.created_at))
+        .collect();
+
+    Some(ResponseTimes {
+        median_days_to_first_response: median(first_response)?,
+        median_days_to_close: median(close)?,
+    })
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+
+    let mid = values.len() / 2;
+    Some(if values.len() % 2 == 1 {
+        values[mid]
+    } else {
+        (values[mid - 1] + values[mid]) / 2.
+    })
+}
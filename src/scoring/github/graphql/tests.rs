@@ -0,0 +1,293 @@
+use super::*;
+
+use chrono::TimeZone;
+
+const RESPONSE: &str = r##"{
+  "data": {
+    "repository": {
+      "issuesOpen": { "totalCount": 1 },
+      "issuesClosed": { "totalCount": 1 },
+      "recentIssues": {
+        "nodes": [
+          {
+            "createdAt": "2023-02-20T00:00:00Z",
+            "closedAt": null,
+            "author": { "__typename": "User", "login": "carol" },
+            "comments": { "nodes": [] }
+          },
+          {
+            "createdAt": "2023-02-01T00:00:00Z",
+            "closedAt": "2023-02-11T00:00:00Z",
+            "author": { "__typename": "User", "login": "alice" },
+            "comments": {
+              "nodes": [
+                { "createdAt": "2023-02-01T12:00:00Z", "author": { "__typename": "User", "login": "alice" } },
+                { "createdAt": "2023-02-03T00:00:00Z", "author": { "__typename": "User", "login": "bob" } }
+              ]
+            }
+          }
+        ]
+      },
+      "recentPullRequests": {
+        "nodes": [
+          {
+            "createdAt": "2023-02-25T00:00:00Z",
+            "closedAt": "2023-02-27T00:00:00Z",
+            "author": { "__typename": "User", "login": "dave" },
+            "comments": { "nodes": [] },
+            "reviews": {
+              "nodes": [
+                { "createdAt": "2023-02-26T00:00:00Z", "author": { "__typename": "User", "login": "bob" } }
+              ]
+            }
+          }
+        ]
+      },
+      "head": {
+        "__typename": "Commit",
+        "checkSuites": {
+          "nodes": [
+            { "status": "COMPLETED", "conclusion": "SUCCESS" },
+            { "status": "COMPLETED", "conclusion": "FAILURE" },
+            { "status": "COMPLETED", "conclusion": "SKIPPED" },
+            { "status": "COMPLETED", "conclusion": "STALE" },
+            { "status": "IN_PROGRESS", "conclusion": null }
+          ]
+        },
+        "tree": {
+          "entries": [
+            { "name": "src", "type": "tree" },
+            { "name": "readme.markdown", "type": "blob" }
+          ]
+        }
+      },
+      "readmeMd": null,
+      "readmeLowerMarkdown": { "__typename": "Blob", "text": "# abc\n\n## Usage\n\nCall it.\n" },
+      "licenseInfo": { "spdxId": "MIT", "pseudoLicense": false }
+    }
+  }
+}"##;
+
+const RESPONSE_NO_ACTIVITY: &str = r#"{
+  "data": {
+    "repository": {
+      "issuesOpen": { "totalCount": 0 },
+      "issuesClosed": { "totalCount": 0 },
+      "recentIssues": { "nodes": [] },
+      "recentPullRequests": { "nodes": [] },
+      "head": null,
+      "licenseInfo": null
+    }
+  }
+}"#;
+
+fn now() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap()
+}
+
+fn parse(data: &str) -> github_query::ResponseData {
+    serde_json::from_str::<graphql_client::Response<_>>(data)
+        .unwrap()
+        .data
+        .unwrap()
+}
+
+#[test]
+fn response_times_from_fixture() {
+    let data = scoring_data_at(parse(RESPONSE), now()).unwrap();
+
+    // first response: 9 days (still waiting), 2 days (own comment ignored), 1 day (review)
+    // close: 9 days (still open), 10 days, 2 days
+    assert_eq!(
+        data.response_times,
+        Some(ResponseTimes {
+            median_days_to_first_response: 2.,
+            median_days_to_close: 9.,
+        })
+    );
+    assert_eq!(data.issues_total, 2);
+}
+
+#[test]
+fn readme_from_default_branch() {
+    let data = scoring_data_at(parse(RESPONSE), now()).unwrap();
+    assert_eq!(
+        data.readme,
+        Some(Readme::new("# abc\n\n## Usage\n\nCall it.\n"))
+    );
+
+    let data = scoring_data_at(parse(RESPONSE_NO_ACTIVITY), now()).unwrap();
+    assert_eq!(data.readme, None);
+}
+
+#[test]
+fn readmes_looked_up_at_revision() {
+    let variables = <GithubQuery as GraphQLQuery>::Variables::from(GithubUrl {
+        owner: "abc".to_owned(),
+        name: "def".to_owned(),
+        reference: Some("v1.2.3".to_owned()),
+        reference_with_path: false,
+    });
+    assert_eq!(variables.revision, "v1.2.3");
+    assert_eq!(variables.readme_md, "v1.2.3:README.md");
+    assert_eq!(variables.readme_lower_markdown, "v1.2.3:readme.markdown");
+}
+
+#[test]
+fn license_from_github() {
+    let data = scoring_data_at(parse(RESPONSE), now()).unwrap();
+    assert_eq!(
+        data.licenses,
+        vec![DeclaredLicense {
+            source: LicenseSource::Github,
+            expression: "MIT".to_string(),
+        }]
+    );
+
+    let data = scoring_data_at(parse(RESPONSE_NO_ACTIVITY), now()).unwrap();
+    assert_eq!(data.licenses, vec![]);
+
+    // GitHub couldn't tell what the license is
+    let other = RESPONSE.replace(
+        r#""spdxId": "MIT", "pseudoLicense": false"#,
+        r#""spdxId": "NOASSERTION", "pseudoLicense": true"#,
+    );
+    let data = scoring_data_at(parse(&other), now()).unwrap();
+    assert_eq!(data.licenses, vec![]);
+}
+
+#[test]
+fn check_suites_on_default_branch() {
+    let data = scoring_data_at(parse(RESPONSE), now()).unwrap();
+    assert_eq!(
+        data.checks,
+        CheckResults {
+            passed: 2,
+            failed: 1,
+        }
+    );
+
+    let data = scoring_data_at(parse(RESPONSE_NO_ACTIVITY), now()).unwrap();
+    assert_eq!(data.checks, CheckResults::default());
+}
+
+#[test]
+fn response_times_no_activity() {
+    let data = scoring_data_at(parse(RESPONSE_NO_ACTIVITY), now()).unwrap();
+    assert_eq!(data.response_times, None);
+}
+
+#[test]
+fn missing_repository() {
+    let data = r#"{"data":{"repository":null}}"#;
+    assert!(scoring_data_at(parse(data), now()).is_err());
+}
+
+const HISTORY_PAGE: &str = r#"{
+  "data": {
+    "repository": {
+      "head": {
+        "__typename": "Commit",
+        "history": {
+          "pageInfo": { "hasNextPage": true, "endCursor": "abc 99" },
+          "nodes": [
+            { "author": { "email": "a@example.com", "name": "A", "user": { "login": "alice" } } },
+            { "author": { "email": "alice@example.com", "name": "A", "user": { "login": "alice" } } },
+            { "author": { "email": "bob@example.com", "name": "B", "user": null } },
+            { "author": { "email": null, "name": "C", "user": null } }
+          ]
+        }
+      }
+    }
+  }
+}"#;
+
+#[test]
+fn history_page_authors() {
+    let page = serde_json::from_str::<graphql_client::Response<_>>(HISTORY_PAGE)
+        .unwrap()
+        .data
+        .unwrap();
+    let mut counts = HashMap::new();
+
+    let next = count_authors(&mut counts, page).unwrap();
+    assert_eq!(next.as_deref(), Some("abc 99"));
+    assert_eq!(
+        counts,
+        HashMap::from([
+            ("alice".to_string(), 2),
+            ("bob@example.com".to_string(), 1),
+            ("C".to_string(), 1),
+        ])
+    );
+}
+
+#[test]
+fn history_last_page() {
+    let data = r#"{"data":{"repository":{"head":{"__typename":"Commit",
+      "history":{"pageInfo":{"hasNextPage":false,"endCursor":"abc 5"},"nodes":[]}}}}}"#;
+    let page = serde_json::from_str::<graphql_client::Response<_>>(data)
+        .unwrap()
+        .data
+        .unwrap();
+    assert_eq!(count_authors(&mut HashMap::new(), page), Ok(None));
+}
+
+#[test]
+fn history_empty_repository() {
+    let data = r#"{"data":{"repository":{"head":null}}}"#;
+    let page = serde_json::from_str::<graphql_client::Response<_>>(data)
+        .unwrap()
+        .data
+        .unwrap();
+    let mut counts = HashMap::new();
+    assert_eq!(count_authors(&mut counts, page), Ok(None));
+    assert!(counts.is_empty());
+}
+
+#[test]
+fn thread_closed_without_comment_is_response() {
+    let created_at = now() - chrono::Duration::days(4);
+    let threads = [Thread {
+        created_at,
+        closed_at: Some(created_at + chrono::Duration::days(1)),
+        first_response: None,
+    }];
+    assert_eq!(
+        response_times(&threads, now()),
+        Some(ResponseTimes {
+            median_days_to_first_response: 1.,
+            median_days_to_close: 1.,
+        })
+    );
+}
+
+#[test]
+fn thread_ghost_author_replies_count() {
+    let at: DateTime = serde_json::from_str(r#""2023-03-01T00:00:00Z""#).unwrap();
+    assert_eq!(first_response(None, [(&at, Some("abc"))]), Some(now()));
+    assert_eq!(first_response(Some("abc"), [(&at, None)]), Some(now()));
+    assert_eq!(first_response(Some("abc"), [(&at, Some("abc"))]), None);
+}
+
+#[test]
+fn median_even_and_odd() {
+    assert_eq!(median(vec![]), None);
+    assert_eq!(median(vec![3., 1., 2.]), Some(2.));
+    assert_eq!(median(vec![4., 1., 3., 2.]), Some(2.5));
+}
+
+#[test]
+fn response_times_score() {
+    let immediate = ResponseTimes {
+        median_days_to_first_response: 0.,
+        median_days_to_close: 0.,
+    };
+    assert_eq!(immediate.score(), 1.);
+
+    let half_lives = ResponseTimes {
+        median_days_to_first_response: crate::scoring::FIRST_RESPONSE_HALF_LIFE_DAYS,
+        median_days_to_close: crate::scoring::CLOSE_HALF_LIFE_DAYS,
+    };
+    assert_eq!(half_lives.score(), 0.5);
+}
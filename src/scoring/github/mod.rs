@@ -1,12 +1,23 @@
 mod datetime;
 pub(super) mod graphql;
+mod rate_limit;
 
 use super::{
+    host::{RepositoryHost, Snapshot},
+    license::{DeclaredLicense, LicenseSource},
+    readme::{self, Readme},
+    upstream,
     url::{get_client, GithubUrl},
-    ScoringData,
+    CheckResults, RatingError, RatingResult, ResponseTimes, ScoringData,
 };
 
+use git_url_parse::GitUrl;
 use once_cell::sync::OnceCell;
+use reqwest::StatusCode;
+use std::{io, path::Path, time::Duration};
+use zip::ZipArchive;
+
+pub(super) use self::rate_limit::{send, Api};
 
 #[derive(Debug, thiserror::Error)]
 pub enum GraphQlError {
@@ -14,9 +25,130 @@ pub enum GraphQlError {
     MissingData,
     #[error("{0}")]
     ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    DeserializeError(#[from] serde_json::Error),
+    #[error("GitHub rate limit exhausted, retry in {}s", retry_after.as_secs())]
+    RateLimited { retry_after: Duration },
+}
+
+impl RepositoryHost for GithubUrl {
+    fn parse(url: &str) -> Option<Self> {
+        GitUrl::parse(url.trim_start_matches("git+"))
+            .ok()?
+            .try_into()
+            .ok()
+    }
+
+    /// Downloads at the commit the reference resolves to, so what was rated can be found again
+    async fn download(&self, path: &Path) -> RatingResult<Snapshot> {
+        let commit = self.resolve_commit().await?;
+        let request = get_client()
+            .get(upstream::get().github_zipball(&self.owner, &self.name, &commit))
+            .bearer_auth(get_token())
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        let content = send(Api::Rest, request)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        ZipArchive::new(io::Cursor::new(&content[..]))?.extract(path)?;
+        Ok(Snapshot {
+            content: content.into(),
+            commit: Some(commit),
+            metrics: None,
+        })
+    }
+
+    async fn metrics(&self) -> RatingResult<ScoringData> {
+        Ok(graphql::query(self.clone()).await?)
+    }
+}
+
+impl GithubUrl {
+    /// What metrics are read at: the reference, or else the default branch
+    pub(super) fn revision(&self) -> &str {
+        self.reference.as_deref().unwrap_or("HEAD")
+    }
+
+    /// SHA of the commit the reference, or else the default branch, points to.
+    /// A reference from a link like `tree/main/src/lib` may run on into a path, so the longest
+    /// leading part of it that resolves is used.
+    async fn resolve_commit(&self) -> RatingResult<String> {
+        let Some(reference) = &self.reference else {
+            return self
+                .commit_of("HEAD")
+                .await?
+                .ok_or_else(|| RatingError::RepositoryNotFound {
+                    repository: format!("{}/{}", self.owner, self.name),
+                });
+        };
+
+        let segments: Vec<&str> = reference.split('/').collect();
+        let shortest = if self.reference_with_path {
+            1
+        } else {
+            segments.len()
+        };
+        for end in (shortest..=segments.len()).rev() {
+            // git doesn't allow these, and in a URL they'd lead somewhere else
+            if segments[..end]
+                .iter()
+                .any(|segment| segment.is_empty() || segment.starts_with('.'))
+            {
+                continue;
+            }
+            if let Some(commit) = self.commit_of(&segments[..end].join("/")).await? {
+                return Ok(commit);
+            }
+        }
+        Err(RatingError::GithubRefNotFound {
+            repository: format!("{}/{}", self.owner, self.name),
+            reference: reference.to_owned(),
+        })
+    }
+
+    /// SHA of the commit a reference points to, or None if there's no such commit
+    async fn commit_of(&self, reference: &str) -> RatingResult<Option<String>> {
+        let request = get_client()
+            .get(upstream::get().github_commit(&self.owner, &self.name, reference))
+            .bearer_auth(get_token())
+            .header("Accept", "application/vnd.github.sha")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        let response = send(Api::Rest, request).await?;
+
+        // 422 is for something that looks like a SHA but isn't a commit
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND || status == StatusCode::UNPROCESSABLE_ENTITY {
+            return Ok(None);
+        }
+        Ok(Some(
+            response.error_for_status()?.text().await?.trim().to_owned(),
+        ))
+    }
+}
+
+/// Responses about a repository are cached under keys starting with this
+fn cache_key(url: &GithubUrl) -> String {
+    match &url.reference {
+        Some(reference) => format!("github/{}/{}/{}", url.owner, url.name, reference),
+        None => format!("github/{}/{}", url.owner, url.name),
+    }
 }
 
 fn get_token() -> &'static str {
     static TOKEN: OnceCell<String> = OnceCell::new();
     TOKEN.get_or_init(|| std::env::var("GITHUB_TOKEN").unwrap())
 }
+
+/// How far back to look at commits when finding the bus factor.
+/// Set in days with `BUS_FACTOR_WINDOW_DAYS`, one year by default.
+pub(super) fn get_history_window() -> chrono::Duration {
+    static WINDOW_DAYS: OnceCell<i64> = OnceCell::new();
+    chrono::Duration::days(*WINDOW_DAYS.get_or_init(|| {
+        std::env::var("BUS_FACTOR_WINDOW_DAYS")
+            .ok()
+            .and_then(|days| days.parse().ok())
+            .unwrap_or(365)
+    }))
+}
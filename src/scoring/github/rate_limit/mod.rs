@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod tests;
+
+use super::{datetime::DateTime, GraphQlError};
+
+use chrono::Utc;
+use reqwest::{header::HeaderMap, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use std::{sync::Mutex, time::Duration};
+use uuid::Uuid;
+
+/// Most times a request is sent before its failure is returned
+const MAX_ATTEMPTS: u32 = 4;
+/// Wait before the first retry, doubled for each one after
+const BASE_DELAY: Duration = Duration::from_millis(500);
+/// Longest wait worth holding a request open for; past this the caller is told to retry later
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// GitHub's APIs, each metered with a quota of its own
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(in crate::scoring) enum Api {
+    Rest,
+    GraphQl,
+}
+
+impl Api {
+    /// When our token's quota for this API resets, if GitHub said it has run out
+    fn exhausted_until(self) -> &'static Mutex<Option<chrono::DateTime<Utc>>> {
+        static REST: Mutex<Option<chrono::DateTime<Utc>>> = Mutex::new(None);
+        static GRAPHQL: Mutex<Option<chrono::DateTime<Utc>>> = Mutex::new(None);
+        match self {
+            Api::Rest => &REST,
+            Api::GraphQl => &GRAPHQL,
+        }
+    }
+}
+
+/// Send a request to one of GitHub's APIs, retrying transient failures with exponential backoff
+/// and jitter. Fails straight away if that API's rate limit is known to be exhausted.
+pub(in crate::scoring) async fn send(
+    api: Api,
+    request: RequestBuilder,
+) -> Result<Response, GraphQlError> {
+    check_exhausted(api, Utc::now())?;
+
+    let mut attempt = 1;
+    loop {
+        let result = request
+            .try_clone()
+            .expect("GitHub requests don't have streaming bodies")
+            .send()
+            .await;
+
+        let wait = match &result {
+            Ok(response) => {
+                let now = Utc::now();
+                observe_headers(api, response.headers(), now);
+                match verdict(response.status(), response.headers(), now) {
+                    Verdict::Done => return Ok(result?),
+                    Verdict::Exhausted(retry_after) => {
+                        return Err(GraphQlError::RateLimited { retry_after })
+                    }
+                    Verdict::Retry(wait) => wait,
+                }
+            }
+            Err(e) if e.is_timeout() || e.is_connect() => None,
+            Err(_) => return Ok(result?),
+        };
+
+        if attempt >= MAX_ATTEMPTS {
+            log::warn!("giving up on GitHub request after {} attempts", attempt);
+            return Ok(result?);
+        }
+        let wait = wait.unwrap_or_else(|| backoff(attempt));
+        log::info!("retrying GitHub request in {:?}", wait);
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Verdict {
+    Done,
+    /// try again, after the given wait if GitHub said how long
+    Retry(Option<Duration>),
+    /// out of quota for longer than is worth waiting
+    Exhausted(Duration),
+}
+
+fn verdict(status: StatusCode, headers: &HeaderMap, now: chrono::DateTime<Utc>) -> Verdict {
+    let retry_after = retry_after(headers, now);
+    // secondary rate limits are a 403 with a `Retry-After`, primary ones a 403 with none remaining
+    let limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN
+            && (retry_after.is_some() || remaining(headers) == Some(0)));
+
+    if limited {
+        match retry_after {
+            Some(wait) if wait > MAX_WAIT => Verdict::Exhausted(wait),
+            wait => Verdict::Retry(wait),
+        }
+    } else if status.is_server_error() {
+        Verdict::Retry(retry_after.filter(|wait| *wait <= MAX_WAIT))
+    } else {
+        Verdict::Done
+    }
+}
+
+/// How long GitHub asked us to wait, from `Retry-After` or from when an exhausted quota resets
+fn retry_after(headers: &HeaderMap, now: chrono::DateTime<Utc>) -> Option<Duration> {
+    let seconds = |name| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<i64>().ok())
+    };
+
+    if let Some(seconds) = seconds("retry-after") {
+        return Some(Duration::from_secs(seconds.max(0) as u64));
+    }
+    if remaining(headers) == Some(0) {
+        let reset = seconds("x-ratelimit-reset")?;
+        return Some(Duration::from_secs((reset - now.timestamp()).max(0) as u64));
+    }
+    None
+}
+
+fn remaining(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Exponential backoff with up to 50% jitter, so retries from concurrent requests spread out
+fn backoff(attempt: u32) -> Duration {
+    let base = BASE_DELAY * 2u32.pow(attempt - 1);
+    let jitter_ms = (Uuid::new_v4().as_u128() % (base.as_millis() / 2 + 1)) as u64;
+    base + Duration::from_millis(jitter_ms)
+}
+
+fn check_exhausted(api: Api, now: chrono::DateTime<Utc>) -> Result<(), GraphQlError> {
+    let mut until = api.exhausted_until().lock().unwrap();
+    match *until {
+        Some(reset) if reset > now => Err(GraphQlError::RateLimited {
+            retry_after: (reset - now).to_std().unwrap_or_default(),
+        }),
+        _ => {
+            *until = None;
+            Ok(())
+        }
+    }
+}
+
+fn exhausted_until(api: Api, reset: chrono::DateTime<Utc>) {
+    log::warn!("GitHub {:?} rate limit exhausted until {}", api, reset);
+    *api.exhausted_until().lock().unwrap() = Some(reset);
+}
+
+/// Remember when the quota resets if a response says none is left
+fn observe_headers(api: Api, headers: &HeaderMap, now: chrono::DateTime<Utc>) {
+    if let (Some(0), Some(wait)) = (remaining(headers), retry_after(headers, now)) {
+        exhausted_until(
+            api,
+            now + chrono::Duration::from_std(wait).unwrap_or_default(),
+        );
+    }
+}
+
+/// The parts of a GraphQL response that tell how much of the quota is left
+#[derive(Debug, Deserialize)]
+pub(super) struct RateLimitResponse {
+    data: Option<RateLimitData>,
+    errors: Option<Vec<RateLimitGraphQlError>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitData {
+    #[serde(rename = "rateLimit")]
+    rate_limit: Option<RateLimit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimit {
+    cost: u64,
+    remaining: u64,
+    #[serde(rename = "resetAt")]
+    reset_at: DateTime,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitGraphQlError {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+/// Check a GraphQL response for the quota it reports.
+/// Fails if GitHub refused the query for being over the limit.
+pub(super) fn observe_graphql(response: RateLimitResponse) -> Result<(), GraphQlError> {
+    let rate_limit = response.data.and_then(|d| d.rate_limit);
+    if let Some(RateLimit {
+        cost,
+        remaining,
+        reset_at,
+    }) = &rate_limit
+    {
+        log::debug!("GraphQL query cost {}, {} remaining", cost, remaining);
+        // the next query would likely cost as much
+        if remaining < cost {
+            exhausted_until(Api::GraphQl, **reset_at);
+        }
+    }
+
+    let limited = response
+        .errors
+        .into_iter()
+        .flatten()
+        .any(|e| e.kind.as_deref() == Some("RATE_LIMITED"));
+    if limited {
+        let reset =
+            rate_limit.map_or_else(|| Utc::now() + chrono::Duration::hours(1), |r| *r.reset_at);
+        exhausted_until(Api::GraphQl, reset);
+        return check_exhausted(Api::GraphQl, Utc::now());
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use super::*;
+
+use chrono::TimeZone;
+use reqwest::header::HeaderValue;
+
+fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.insert(*name, HeaderValue::from_str(value).unwrap());
+    }
+    headers
+}
+
+fn now() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 3, 1, 12, 0, 0).unwrap()
+}
+
+#[test]
+fn success_is_done() {
+    let headers = headers(&[("x-ratelimit-remaining", "4999")]);
+    assert_eq!(verdict(StatusCode::OK, &headers, now()), Verdict::Done);
+    assert_eq!(
+        verdict(StatusCode::NOT_FOUND, &headers, now()),
+        Verdict::Done
+    );
+}
+
+#[test]
+fn server_errors_retried() {
+    assert_eq!(
+        verdict(StatusCode::BAD_GATEWAY, &HeaderMap::new(), now()),
+        Verdict::Retry(None)
+    );
+}
+
+#[test]
+fn secondary_limit_waits() {
+    let headers = headers(&[("retry-after", "10")]);
+    assert_eq!(
+        verdict(StatusCode::FORBIDDEN, &headers, now()),
+        Verdict::Retry(Some(Duration::from_secs(10)))
+    );
+}
+
+#[test]
+fn primary_limit_exhausted() {
+    let reset = now().timestamp() + 20 * 60;
+    let headers = headers(&[
+        ("x-ratelimit-remaining", "0"),
+        ("x-ratelimit-reset", &reset.to_string()),
+    ]);
+    assert_eq!(
+        verdict(StatusCode::FORBIDDEN, &headers, now()),
+        Verdict::Exhausted(Duration::from_secs(20 * 60))
+    );
+}
+
+#[test]
+fn forbidden_without_limit_is_done() {
+    let headers = headers(&[("x-ratelimit-remaining", "12")]);
+    assert_eq!(
+        verdict(StatusCode::FORBIDDEN, &headers, now()),
+        Verdict::Done
+    );
+}
+
+#[test]
+fn backoff_grows_with_jitter() {
+    for attempt in 1..=3 {
+        let base = BASE_DELAY * 2u32.pow(attempt - 1);
+        let wait = backoff(attempt);
+        assert!(wait >= base && wait <= base + base / 2, "{:?}", wait);
+    }
+}
+
+#[test]
+fn graphql_rate_limited() {
+    let response: RateLimitResponse = serde_json::from_str(
+        r#"{
+            "data": {"rateLimit": {"cost": 1, "remaining": 0, "resetAt": "2999-01-01T00:00:00Z"}},
+            "errors": [{"type": "RATE_LIMITED", "message": "API rate limit exceeded"}]
+        }"#,
+    )
+    .unwrap();
+    let result = observe_graphql(response);
+    let rest = check_exhausted(Api::Rest, Utc::now());
+    *Api::GraphQl.exhausted_until().lock().unwrap() = None;
+
+    assert!(matches!(result, Err(GraphQlError::RateLimited { .. })));
+    // metered separately
+    assert!(rest.is_ok());
+}
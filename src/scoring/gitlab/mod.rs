@@ -0,0 +1,394 @@
+#[cfg(test)]
+mod tests;
+
+use super::{
+    github::{
+        get_history_window,
+        graphql::{response_times, Thread},
+    },
+    host::{RepositoryHost, Snapshot},
+    license::{DeclaredLicense, LicenseSource},
+    readme::Readme,
+    upstream,
+    url::{get_client, GitlabUrl},
+    CheckResults, RatingResult, ScoringData,
+};
+use crate::cache;
+
+use chrono::{DateTime, Utc};
+use git_url_parse::GitUrl;
+use once_cell::sync::OnceCell;
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{collections::HashMap, io, path::Path};
+use zip::ZipArchive;
+
+/// Most issues, and most merge requests, looked at for response times
+const RECENT_THREADS: usize = 30;
+/// Most notes on a thread looked through for a first response
+const NOTES_PER_THREAD: usize = 10;
+/// Most pipelines on the default branch whose outcomes are counted
+const RECENT_PIPELINES: usize = 20;
+/// Commits per page of history, the most GitLab allows
+const HISTORY_PAGE_SIZE: usize = 100;
+/// Most pages of commit history to request when counting commit authors
+const MAX_HISTORY_PAGES: usize = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GitlabError {
+    #[error("project `{0}` does not exist or is private")]
+    ProjectNotFound(String),
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    DeserializeError(#[from] serde_json::Error),
+}
+
+/// Access token for private projects, from `GITLAB_TOKEN`.
+/// Public projects can be scored without one.
+fn get_token() -> Option<&'static str> {
+    static TOKEN: OnceCell<Option<String>> = OnceCell::new();
+    TOKEN
+        .get_or_init(|| std::env::var("GITLAB_TOKEN").ok())
+        .as_deref()
+}
+
+fn encode(value: &str) -> String {
+    ::url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+/// Requests about one project, with responses cached under keys starting with `key`
+struct Api {
+    base: String,
+    key: String,
+}
+
+impl Api {
+    fn new(url: &GitlabUrl) -> Self {
+        Api {
+            base: format!(
+                "{}/projects/{}",
+                upstream::get().gitlab_api(&url.host),
+                encode(&url.path)
+            ),
+            key: format!("gitlab/{}/{}", url.host, url.path),
+        }
+    }
+
+    fn request(&self, path: &str) -> RequestBuilder {
+        let request = get_client().get(format!("{}{}", self.base, path));
+        match get_token() {
+            Some(token) => request.header("PRIVATE-TOKEN", token),
+            None => request,
+        }
+    }
+
+    async fn get_text(&self, path: &str) -> Result<String, GitlabError> {
+        let key = format!("{}{}", self.key, path);
+        if let Some(text) = cache::get().get(&key) {
+            return Ok(text);
+        }
+
+        let text = self
+            .request(path)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        cache::get().insert(&key, text.clone());
+        Ok(text)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, GitlabError> {
+        Ok(serde_json::from_str(&self.get_text(path).await?)?)
+    }
+}
+
+impl RepositoryHost for GitlabUrl {
+    fn parse(url: &str) -> Option<Self> {
+        GitUrl::parse(url.trim_start_matches("git+"))
+            .ok()?
+            .try_into()
+            .ok()
+    }
+
+    async fn download(&self, path: &Path) -> RatingResult<Snapshot> {
+        let content = Api::new(self)
+            .request("/repository/archive.zip")
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|e| project_not_found(self, e.into()))?
+            .bytes()
+            .await?;
+
+        ZipArchive::new(io::Cursor::new(&content[..]))?.extract(path)?;
+        Ok(Snapshot {
+            content: content.into(),
+            commit: None,
+            metrics: None,
+        })
+    }
+
+    async fn metrics(&self) -> RatingResult<ScoringData> {
+        Ok(query(self).await?)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    default_branch: Option<String>,
+    /// link to the README's page, like `https://gitlab.com/group/project/-/blob/main/README.md`
+    readme_url: Option<String>,
+    license: Option<ProjectLicense>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectLicense {
+    /// SPDX identifier in lower case, like `mit` or `apache-2.0`
+    key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssuesStatistics {
+    statistics: Statistics,
+}
+
+#[derive(Debug, Deserialize)]
+struct Statistics {
+    counts: Counts,
+}
+
+#[derive(Debug, Deserialize)]
+struct Counts {
+    opened: usize,
+    closed: usize,
+}
+
+/// An issue or merge request
+#[derive(Debug, Deserialize)]
+struct Issuable {
+    iid: u64,
+    created_at: DateTime<Utc>,
+    closed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    merged_at: Option<DateTime<Utc>>,
+    author: Option<User>,
+    user_notes_count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Note {
+    created_at: DateTime<Utc>,
+    author: Option<User>,
+    /// made by GitLab for events like labels changing, rather than written by someone
+    system: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pipeline {
+    status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Commit {
+    author_email: String,
+}
+
+/// GitLab answers 404 for a project that doesn't exist, and for one we can't see
+fn project_not_found(url: &GitlabUrl, e: GitlabError) -> GitlabError {
+    match e {
+        GitlabError::ReqwestError(e) if e.status() == Some(StatusCode::NOT_FOUND) => {
+            GitlabError::ProjectNotFound(format!("{}/{}", url.host, url.path))
+        }
+        e => e,
+    }
+}
+
+/// Collect the same data about a GitLab project as is collected about GitHub repositories
+async fn query(url: &GitlabUrl) -> Result<ScoringData, GitlabError> {
+    let api = Api::new(url);
+
+    let project: Project = api
+        .get("?license=true")
+        .await
+        .map_err(|e| project_not_found(url, e))?;
+    let IssuesStatistics {
+        statistics: Statistics { counts },
+    } = api.get("/issues_statistics").await?;
+
+    let recent = format!("?order_by=created_at&sort=desc&per_page={}", RECENT_THREADS);
+    let issues: Vec<Issuable> = api.get(&format!("/issues{}", recent)).await?;
+    let merge_requests: Vec<Issuable> = api.get(&format!("/merge_requests{}", recent)).await?;
+    let threads = futures::future::try_join_all(
+        issues
+            .iter()
+            .map(|issue| thread(&api, "issues", issue))
+            .chain(
+                merge_requests
+                    .iter()
+                    .map(|mr| thread(&api, "merge_requests", mr)),
+            ),
+    )
+    .await?;
+
+    let (readme, checks, commit_counts) = match &project.default_branch {
+        Some(branch) => {
+            let pipelines: Vec<Pipeline> = api
+                .get(&format!(
+                    "/pipelines?ref={}&per_page={}",
+                    encode(branch),
+                    RECENT_PIPELINES
+                ))
+                .await?;
+            (
+                readme(&api, &project, branch).await,
+                check_results(&pipelines),
+                commit_counts(&api, branch).await?,
+            )
+        }
+        // an empty project
+        None => (None, CheckResults::default(), Vec::new()),
+    };
+
+    // GitLab's "Other" license is not an SPDX identifier
+    let licenses = project
+        .license
+        .filter(|l| l.key != "other")
+        .map(|l| DeclaredLicense {
+            source: LicenseSource::Gitlab,
+            expression: l.key,
+        })
+        .into_iter()
+        .collect();
+
+    Ok(ScoringData {
+        readme,
+        // only known once the package contents have been looked through
+        documentation: None,
+        issues_closed: counts.closed,
+        issues_total: counts.closed + counts.opened,
+        checks,
+        // only known once the package contents have been looked through
+        test_suite: None,
+        commit_counts,
+        response_times: response_times(&threads, Utc::now()),
+        days_since_commit: None,
+        licenses,
+        // only known once the package.json has been read
+        dependency_licenses: Vec::new(),
+    })
+}
+
+async fn thread(api: &Api, kind: &str, issuable: &Issuable) -> Result<Thread, GitlabError> {
+    let notes: Vec<Note> = if issuable.user_notes_count == 0 {
+        Vec::new()
+    } else {
+        api.get(&format!(
+            "/{}/{}/notes?sort=asc&order_by=created_at&per_page={}",
+            kind, issuable.iid, NOTES_PER_THREAD
+        ))
+        .await?
+    };
+    Ok(to_thread(issuable, &notes))
+}
+
+/// Find the earliest note written by someone other than the author of the thread.
+/// Merge requests that were merged rather than closed count as closed when they were merged.
+fn to_thread(issuable: &Issuable, notes: &[Note]) -> Thread {
+    let author = issuable.author.as_ref().map(|a| a.username.as_str());
+    let first_response = notes
+        .iter()
+        .filter(|note| !note.system)
+        .filter(|note| {
+            author.is_none() || note.author.as_ref().map(|a| a.username.as_str()) != author
+        })
+        .map(|note| note.created_at)
+        .min();
+
+    Thread {
+        created_at: issuable.created_at,
+        closed_at: issuable.closed_at.or(issuable.merged_at),
+        first_response,
+    }
+}
+
+/// Tally the pipelines that have finished running on the default branch.
+/// GitLab has no check suites, so recent pipelines stand in for those on the latest commit.
+fn check_results(pipelines: &[Pipeline]) -> CheckResults {
+    pipelines
+        .iter()
+        .fold(CheckResults::default(), |results, pipeline| {
+            match pipeline.status.as_str() {
+                "success" | "skipped" => CheckResults {
+                    passed: results.passed + 1,
+                    ..results
+                },
+                "failed" | "canceled" => CheckResults {
+                    failed: results.failed + 1,
+                    ..results
+                },
+                // still running, or waiting to be
+                _ => results,
+            }
+        })
+}
+
+/// Read the README on the default branch, if the project has one
+async fn readme(api: &Api, project: &Project, branch: &str) -> Option<Readme> {
+    let file = project
+        .readme_url
+        .as_deref()?
+        .split_once(&format!("/-/blob/{}/", branch))?
+        .1;
+    let text = api
+        .get_text(&format!(
+            "/repository/files/{}/raw?ref={}",
+            encode(file),
+            encode(branch)
+        ))
+        .await
+        .ok()?;
+    Some(Readme::new(&text))
+}
+
+/// Count the commits each author made to the default branch within the history window
+async fn commit_counts(api: &Api, branch: &str) -> Result<Vec<usize>, GitlabError> {
+    // whole days, so the same requests are made (and cached) all day
+    let since = (Utc::now() - get_history_window()).format("%Y-%m-%dT00:00:00Z");
+
+    let mut counts = HashMap::new();
+    for page in 1..=MAX_HISTORY_PAGES {
+        let commits: Vec<Commit> = api
+            .get(&format!(
+                "/repository/commits?ref_name={}&since={}&per_page={}&page={}",
+                encode(branch),
+                since,
+                HISTORY_PAGE_SIZE,
+                page
+            ))
+            .await?;
+        let last_page = commits.len() < HISTORY_PAGE_SIZE;
+        count_authors(&mut counts, commits);
+        if last_page {
+            break;
+        }
+    }
+
+    Ok(counts.into_values().collect())
+}
+
+/// GitLab only knows commit authors by email, which is case insensitive
+fn count_authors(counts: &mut HashMap<String, usize>, commits: Vec<Commit>) {
+    for commit in commits {
+        *counts
+            .entry(commit.author_email.to_lowercase())
+            .or_default() += 1;
+    }
+}
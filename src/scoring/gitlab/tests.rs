@@ -0,0 +1,132 @@
+use super::*;
+
+use chrono::TimeZone;
+
+fn at(day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 3, day, 0, 0, 0).unwrap()
+}
+
+/// Recorded from `gitlab.com/api/v4/projects/:id/issues`, trimmed
+const ISSUES: &str = r#"[
+  {
+    "iid": 12,
+    "created_at": "2023-03-01T00:00:00.000Z",
+    "closed_at": null,
+    "author": { "username": "reporter" },
+    "user_notes_count": 2
+  },
+  {
+    "iid": 11,
+    "created_at": "2023-03-01T00:00:00.000Z",
+    "closed_at": null,
+    "merged_at": "2023-03-05T00:00:00.000Z",
+    "author": null,
+    "user_notes_count": 0
+  }
+]"#;
+
+const NOTES: &str = r#"[
+  {
+    "created_at": "2023-03-02T00:00:00.000Z",
+    "author": { "username": "gitlab-bot" },
+    "system": true
+  },
+  {
+    "created_at": "2023-03-03T00:00:00.000Z",
+    "author": { "username": "reporter" },
+    "system": false
+  },
+  {
+    "created_at": "2023-03-04T00:00:00.000Z",
+    "author": { "username": "maintainer" },
+    "system": false
+  }
+]"#;
+
+#[test]
+fn thread_first_response_from_someone_else() {
+    let issues: Vec<Issuable> = serde_json::from_str(ISSUES).unwrap();
+    let notes: Vec<Note> = serde_json::from_str(NOTES).unwrap();
+
+    assert_eq!(
+        to_thread(&issues[0], &notes),
+        Thread {
+            created_at: at(1),
+            closed_at: None,
+            // system notes and the author's own replies don't count
+            first_response: Some(at(4)),
+        }
+    );
+}
+
+#[test]
+fn thread_merged_is_closed() {
+    let issues: Vec<Issuable> = serde_json::from_str(ISSUES).unwrap();
+    assert_eq!(
+        to_thread(&issues[1], &[]),
+        Thread {
+            created_at: at(1),
+            closed_at: Some(at(5)),
+            first_response: None,
+        }
+    );
+}
+
+#[test]
+fn pipelines_tallied() {
+    let pipelines: Vec<Pipeline> = serde_json::from_str(
+        r#"[
+            {"id": 5, "status": "running"},
+            {"id": 4, "status": "success"},
+            {"id": 3, "status": "failed"},
+            {"id": 2, "status": "success"},
+            {"id": 1, "status": "canceled"}
+        ]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        check_results(&pipelines),
+        CheckResults {
+            passed: 2,
+            failed: 2,
+        }
+    );
+}
+
+#[test]
+fn project_license() {
+    let project: Project = serde_json::from_str(
+        r#"{
+            "id": 278964,
+            "default_branch": "master",
+            "readme_url": "https://gitlab.com/gitlab-org/gitlab/-/blob/master/README.md",
+            "license": {"key": "mit", "name": "MIT License", "nickname": null}
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(project.default_branch.as_deref(), Some("master"));
+    assert_eq!(project.license.map(|l| l.key).as_deref(), Some("mit"));
+}
+
+#[test]
+fn authors_counted_by_email() {
+    let commits: Vec<Commit> = serde_json::from_str(
+        r#"[
+            {"author_email": "Ada@example.com"},
+            {"author_email": "ada@example.com"},
+            {"author_email": "grace@example.com"}
+        ]"#,
+    )
+    .unwrap();
+
+    let mut counts = HashMap::new();
+    count_authors(&mut counts, commits);
+    assert_eq!(counts["ada@example.com"], 2);
+    assert_eq!(counts["grace@example.com"], 1);
+}
+
+#[test]
+fn project_path_encoded() {
+    assert_eq!(encode("group/sub/project"), "group%2Fsub%2Fproject");
+    assert_eq!(encode("release/1.x"), "release%2F1.x");
+}
@@ -0,0 +1,79 @@
+use super::{
+    git::GitRepository,
+    url::{GithubUrl, GitlabUrl, RepositoryUrl},
+    RatingResult, ScoringData,
+};
+
+use std::path::Path;
+
+/// A repository downloaded as a zip
+pub(super) struct Snapshot {
+    pub(super) content: Vec<u8>,
+    /// the commit it was taken at, if the host says
+    pub(super) commit: Option<String>,
+    /// what the host knows about the repository at that commit, if it was found out while
+    /// downloading
+    pub(super) metrics: Option<ScoringData>,
+}
+
+/// The repository a package was downloaded from, pinned to the downloaded commit where the host
+/// says what it was
+pub(super) struct Downloaded {
+    pub(super) repository: RepositoryUrl,
+    /// its metrics, if they were already found out while downloading
+    pub(super) metrics: Option<ScoringData>,
+}
+
+/// A place a package's source can be hosted, and how to rate a package from there
+pub(super) trait RepositoryHost: Sized {
+    /// Recognize a link to a repository on this host
+    fn parse(url: &str) -> Option<Self>;
+
+    /// Download the default branch, or the revision the URL names, and extract it into `path`
+    async fn download(&self, path: &Path) -> RatingResult<Snapshot>;
+
+    /// Collect what the host knows about the repository beyond the files in it
+    async fn metrics(&self) -> RatingResult<ScoringData>;
+}
+
+impl RepositoryUrl {
+    /// Whether two links lead to the same repository, whatever revision they name
+    pub(super) fn same_repository(&self, other: &RepositoryUrl) -> bool {
+        match (self, other) {
+            (RepositoryUrl::Github(a), RepositoryUrl::Github(b)) => {
+                a.owner.eq_ignore_ascii_case(&b.owner) && a.name.eq_ignore_ascii_case(&b.name)
+            }
+            (RepositoryUrl::Gitlab(a), RepositoryUrl::Gitlab(b)) => a == b,
+            (RepositoryUrl::Git(a), RepositoryUrl::Git(b)) => {
+                a.url.trim_end_matches(".git") == b.url.trim_end_matches(".git")
+            }
+            _ => false,
+        }
+    }
+}
+
+impl RepositoryHost for RepositoryUrl {
+    /// Hosts with an API are preferred, plain git is the fallback
+    fn parse(url: &str) -> Option<Self> {
+        GithubUrl::parse(url)
+            .map(RepositoryUrl::Github)
+            .or_else(|| GitlabUrl::parse(url).map(RepositoryUrl::Gitlab))
+            .or_else(|| GitRepository::parse(url).map(RepositoryUrl::Git))
+    }
+
+    async fn download(&self, path: &Path) -> RatingResult<Snapshot> {
+        match self {
+            RepositoryUrl::Github(url) => url.download(path).await,
+            RepositoryUrl::Gitlab(url) => url.download(path).await,
+            RepositoryUrl::Git(repository) => repository.download(path).await,
+        }
+    }
+
+    async fn metrics(&self) -> RatingResult<ScoringData> {
+        match self {
+            RepositoryUrl::Github(url) => url.metrics().await,
+            RepositoryUrl::Gitlab(url) => url.metrics().await,
+            RepositoryUrl::Git(repository) => repository.metrics().await,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests;
+
+use super::{url::NpmDist, RatingError, RatingResult};
+
+use base64::{engine::general_purpose, Engine};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// Hash algorithms of Subresource Integrity strings, strongest first
+static ALGORITHMS: [&str; 4] = ["sha512", "sha384", "sha256", "sha1"];
+
+/// Check a downloaded tarball against the `integrity` and `shasum` the registry gave for it.
+///
+/// Returns the digest that was verified, as an SRI string, or `None` if the registry gave
+/// nothing to verify against.
+pub(super) fn verify(dist: &NpmDist, content: &[u8]) -> RatingResult<Option<String>> {
+    if let Some(integrity) = dist.integrity.as_deref() {
+        // per the SRI spec, only the strongest algorithm given is used, and any of its hashes
+        // may match
+        let strongest = ALGORITHMS.iter().find_map(|&algorithm| {
+            let expected: Vec<&str> = hashes(integrity, algorithm).collect();
+            (!expected.is_empty()).then_some((algorithm, expected))
+        });
+        if let Some((algorithm, expected)) = strongest {
+            let actual = digest(algorithm, content);
+            return if expected.contains(&actual.as_str()) {
+                Ok(Some(format!("{}-{}", algorithm, actual)))
+            } else {
+                Err(mismatch(dist, integrity))
+            };
+        }
+    }
+
+    match dist.shasum.as_deref() {
+        Some(shasum) => {
+            let actual = Sha1::digest(content);
+            if hex(&actual).eq_ignore_ascii_case(shasum.trim()) {
+                Ok(Some(format!(
+                    "sha1-{}",
+                    general_purpose::STANDARD.encode(actual)
+                )))
+            } else {
+                Err(mismatch(dist, shasum))
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+/// Base64 hashes for `algorithm` in an SRI string like `sha512-abc== sha1-def=`, without
+/// options such as `?foo`
+fn hashes<'a>(integrity: &'a str, algorithm: &'a str) -> impl Iterator<Item = &'a str> {
+    integrity.split_whitespace().filter_map(move |entry| {
+        let (name, hash) = entry.split_once('-')?;
+        (name == algorithm).then(|| hash.split('?').next().unwrap_or_default())
+    })
+}
+
+/// Base64 digest of `content` with one of the `ALGORITHMS`
+fn digest(algorithm: &str, content: &[u8]) -> String {
+    let hash = match algorithm {
+        "sha512" => Sha512::digest(content).to_vec(),
+        "sha384" => Sha384::digest(content).to_vec(),
+        "sha256" => Sha256::digest(content).to_vec(),
+        _ => Sha1::digest(content).to_vec(),
+    };
+    general_purpose::STANDARD.encode(hash)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn mismatch(dist: &NpmDist, expected: &str) -> RatingError {
+    RatingError::IntegrityMismatch {
+        tarball: dist.tarball.clone(),
+        expected: expected.to_owned(),
+    }
+}
@@ -0,0 +1,66 @@
+use super::*;
+
+const CONTENT: &[u8] = b"hello";
+/// `echo -n hello | openssl dgst -sha512 -binary | base64`
+const SHA512: &str =
+    "m3HSJL1i83hdltRq0+o9czGb+8KJDKra4t/3JRlnPKcjI8PZm6XBHXx6zG4UuMXaDEZjR1wuXDre9G9zvN7AQw==";
+/// `echo -n hello | sha1sum`
+const SHASUM: &str = "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d";
+
+fn dist(integrity: Option<&str>, shasum: Option<&str>) -> NpmDist {
+    NpmDist {
+        tarball: "https://registry.npmjs.org/hello/-/hello-1.0.0.tgz".to_owned(),
+        integrity: integrity.map(str::to_owned),
+        shasum: shasum.map(str::to_owned),
+    }
+}
+
+#[test]
+fn sri_matches() {
+    let integrity = format!("sha512-{}", SHA512);
+    assert_eq!(
+        verify(&dist(Some(&integrity), Some(SHASUM)), CONTENT).unwrap(),
+        Some(integrity)
+    );
+}
+
+#[test]
+fn sri_strongest_algorithm_used() {
+    // a wrong sha256 is ignored when there is a sha512, and options are dropped
+    let integrity = format!("sha256-AAAA sha512-{}?ct=application/gzip", SHA512);
+    assert_eq!(
+        verify(&dist(Some(&integrity), None), CONTENT).unwrap(),
+        Some(format!("sha512-{}", SHA512))
+    );
+}
+
+#[test]
+fn sri_mismatch() {
+    let integrity = format!("sha512-{}", SHA512);
+    let result = verify(&dist(Some(&integrity), Some(SHASUM)), b"tampered");
+    assert!(matches!(
+        result,
+        Err(RatingError::IntegrityMismatch { expected, .. }) if expected == integrity
+    ));
+}
+
+#[test]
+fn shasum_when_no_integrity() {
+    assert_eq!(
+        verify(&dist(None, Some(SHASUM)), CONTENT).unwrap(),
+        Some("sha1-qvTGHdzF6KLavt4PO0gs2a6pQ00=".to_owned())
+    );
+    assert!(verify(&dist(None, Some(SHASUM)), b"tampered").is_err());
+
+    // older packages may only have a sha1 in `integrity` too
+    let integrity = "sha1-qvTGHdzF6KLavt4PO0gs2a6pQ00=";
+    assert_eq!(
+        verify(&dist(Some(integrity), None), CONTENT).unwrap(),
+        Some(integrity.to_owned())
+    );
+}
+
+#[test]
+fn nothing_to_verify() {
+    assert_eq!(verify(&dist(None, None), CONTENT).unwrap(), None);
+}
@@ -0,0 +1,616 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    database,
+    queries::types::{DependencyLicense, LicenseConflict},
+};
+
+use firestore::FirestoreDb;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// Licenses accepted when `LICENSE_ALLOW` is not set
+static DEFAULT_ALLOWED: [&str; 46] = [
+    "gpl-3.0-only",
+    "gpl-3.0-or-later",
+    "gpl-2.0-only",
+    "gpl-2.0-or-later",
+    "lgpl-2.1-only",
+    "lgpl-2.1-or-later",
+    "lgpl-3.0-only",
+    "lgpl-3.0-or-later",
+    "agpl-3.0",
+    "apache-2.0",
+    "artistic-2.0",
+    "clartistic",
+    "bsl-1.0",
+    "cecill-2.0",
+    "ecos-2.0",
+    "ecl-2.0",
+    "efl-2.0",
+    "eudatagrid",
+    "bsd-2-clause-freebsd",
+    "ftl",
+    "hpnd",
+    "imatix",
+    "imlib2",
+    "ijg",
+    "intel",
+    "isc",
+    "mpl-2.0",
+    "ncsa",
+    "python-2.0.1",
+    "python-2.1.1",
+    "ruby",
+    "sgi-b-2.0",
+    "standardml-nj",
+    "smlnj",
+    "unicode-dfs-2015",
+    "unicode-dfs-2016",
+    "upl-1.0",
+    "unlicense",
+    "vim",
+    "wtfpl",
+    "x11",
+    "mit",
+    "xfree86-1.1",
+    "zlib",
+    "zpl-2.0",
+    "zpl-2.1",
+];
+
+/// License families whose bare identifiers (`GPL-2.0`) are deprecated forms of `-only`
+static GNU_FAMILIES: [&str; 4] = ["gpl-", "lgpl-", "agpl-", "gfdl-"];
+
+/// File names (without extension) of files holding license text
+static LICENSE_FILE_STEMS: [&str; 4] = ["license", "licence", "copying", "unlicense"];
+
+/// Where a package said what its license is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LicenseSource {
+    PackageJson,
+    LicenseFile,
+    Github,
+    Gitlab,
+    /// a license file in the repository, for hosts without an API that identifies it
+    Repository,
+}
+
+impl Display for LicenseSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LicenseSource::PackageJson => "package.json",
+            LicenseSource::LicenseFile => "license file",
+            LicenseSource::Github => "GitHub",
+            LicenseSource::Gitlab => "GitLab",
+            LicenseSource::Repository => "repository",
+        })
+    }
+}
+
+/// An SPDX license expression as it was written in one of the sources
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct DeclaredLicense {
+    pub(super) source: LicenseSource,
+    pub(super) expression: String,
+}
+
+impl From<(DeclaredLicense, DeclaredLicense)> for LicenseConflict {
+    fn from((first, second): (DeclaredLicense, DeclaredLicense)) -> Self {
+        LicenseConflict {
+            source: first.source.to_string(),
+            license: first.expression,
+            other_source: second.source.to_string(),
+            other_license: second.expression,
+        }
+    }
+}
+
+/// The `"license"` field of a `package.json`, which older packages wrote as an object
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub(super) enum PackageJsonLicense {
+    Spdx(String),
+    Legacy {
+        #[serde(rename = "type")]
+        kind: String,
+    },
+    /// any other shape, which isn't worth failing to read the whole `package.json` over
+    Other(serde_json::Value),
+}
+
+impl PackageJsonLicense {
+    /// The SPDX expression, unless it refers to a file with `"SEE LICENSE IN <file>"`, in
+    /// which case the license files are checked instead
+    pub(super) fn into_expression(self) -> Option<String> {
+        let (PackageJsonLicense::Spdx(expression)
+        | PackageJsonLicense::Legacy { kind: expression }) = self
+        else {
+            return None;
+        };
+        (!expression.starts_with("SEE LICENSE IN")).then_some(expression)
+    }
+}
+
+/// A parsed SPDX license expression, with license identifiers normalized to lowercase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Expression {
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct ParseError;
+
+impl std::str::FromStr for Expression {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spaced = s.replace('(', " ( ").replace(')', " ) ");
+        let mut tokens = spaced.split_whitespace().peekable();
+
+        let expression = parse_or(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(expression),
+            Some(_) => Err(ParseError),
+        }
+    }
+}
+
+type Tokens<'a> = std::iter::Peekable<std::str::SplitWhitespace<'a>>;
+
+fn is_operator(token: &str, operator: &str) -> bool {
+    token.eq_ignore_ascii_case(operator)
+}
+
+// OR binds the loosest, then AND, then WITH
+fn parse_or(tokens: &mut Tokens) -> Result<Expression, ParseError> {
+    let mut left = parse_and(tokens)?;
+    while tokens.next_if(|t| is_operator(t, "OR")).is_some() {
+        left = Expression::Or(Box::new(left), Box::new(parse_and(tokens)?));
+    }
+    Ok(left)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Expression, ParseError> {
+    let mut left = parse_with(tokens)?;
+    while tokens.next_if(|t| is_operator(t, "AND")).is_some() {
+        left = Expression::And(Box::new(left), Box::new(parse_with(tokens)?));
+    }
+    Ok(left)
+}
+
+fn parse_with(tokens: &mut Tokens) -> Result<Expression, ParseError> {
+    match tokens.next().ok_or(ParseError)? {
+        "(" => {
+            let inner = parse_or(tokens)?;
+            tokens.next_if_eq(&")").ok_or(ParseError)?;
+            Ok(inner)
+        }
+        ")" => Err(ParseError),
+        t if ["AND", "OR", "WITH"].iter().any(|op| is_operator(t, op)) => Err(ParseError),
+        id => {
+            let exception = match tokens.next_if(|t| is_operator(t, "WITH")) {
+                Some(_) => Some(tokens.next().ok_or(ParseError)?.to_lowercase()),
+                None => None,
+            };
+            Ok(Expression::License {
+                id: normalize_id(id),
+                exception,
+            })
+        }
+    }
+}
+
+/// Lowercase an identifier and turn deprecated GNU forms into their current names,
+/// so `GPL-2.0+` matches `gpl-2.0-or-later` and `GPL-3.0` matches `gpl-3.0-only`
+fn normalize_id(id: &str) -> String {
+    let id = id.to_lowercase();
+    let (id, or_later) = match id.strip_suffix('+') {
+        Some(id) => (id.to_owned(), true),
+        None => (id, false),
+    };
+
+    let gnu = GNU_FAMILIES.iter().any(|family| id.starts_with(family));
+    if !gnu || id.ends_with("-only") || id.ends_with("-or-later") {
+        id
+    } else if or_later {
+        id + "-or-later"
+    } else {
+        id + "-only"
+    }
+}
+
+impl Expression {
+    /// A string that is the same for expressions that only differ in the order of operands
+    fn canonical(&self) -> String {
+        match self {
+            Expression::License {
+                id,
+                exception: Some(exception),
+            } => format!("{id} with {exception}"),
+            Expression::License { id, .. } => id.clone(),
+            Expression::And(..) => self.canonical_chain("and"),
+            Expression::Or(..) => self.canonical_chain("or"),
+        }
+    }
+
+    fn canonical_chain(&self, operator: &str) -> String {
+        let mut operands = Vec::new();
+        self.flatten(&mut operands);
+        let mut operands: Vec<_> = operands.into_iter().map(Expression::canonical).collect();
+        operands.sort();
+        format!("({})", operands.join(&format!(" {operator} ")))
+    }
+
+    /// Collect the operands of a chain of the same operator, like `a AND (b AND c)`
+    fn flatten<'a>(&'a self, operands: &mut Vec<&'a Expression>) {
+        let (left, right) = match self {
+            Expression::And(left, right) | Expression::Or(left, right) => (left, right),
+            Expression::License { .. } => return operands.push(self),
+        };
+        for side in [left, right] {
+            if std::mem::discriminant(&**side) == std::mem::discriminant(self) {
+                side.flatten(operands);
+            } else {
+                operands.push(side);
+            }
+        }
+    }
+}
+
+/// Which licenses are acceptable to use.
+///
+/// Configured with comma separated SPDX identifiers in `LICENSE_ALLOW` and `LICENSE_DENY`.
+/// Denied licenses (or exceptions) are never acceptable, even when also allowed.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct LicensePolicy {
+    allow: HashSet<String>,
+    deny: HashSet<String>,
+}
+
+impl LicensePolicy {
+    pub(super) fn new<A, D>(allow: A, deny: D) -> Self
+    where
+        A: IntoIterator,
+        A::Item: AsRef<str>,
+        D: IntoIterator,
+        D::Item: AsRef<str>,
+    {
+        LicensePolicy {
+            allow: allow
+                .into_iter()
+                .map(|id| normalize_id(id.as_ref().trim()))
+                .collect(),
+            deny: deny
+                .into_iter()
+                .map(|id| normalize_id(id.as_ref().trim()))
+                .collect(),
+        }
+    }
+
+    pub(super) fn get() -> &'static LicensePolicy {
+        static POLICY: OnceCell<LicensePolicy> = OnceCell::new();
+        POLICY.get_or_init(|| {
+            let list = |var| {
+                std::env::var(var).ok().map(|ids| {
+                    ids.split(',')
+                        .filter(|id| !id.trim().is_empty())
+                        .map(str::to_owned)
+                        .collect::<Vec<_>>()
+                })
+            };
+            let allow = list("LICENSE_ALLOW")
+                .unwrap_or_else(|| DEFAULT_ALLOWED.iter().map(|id| id.to_string()).collect());
+            let deny = list("LICENSE_DENY").unwrap_or_default();
+            LicensePolicy::new(allow, deny)
+        })
+    }
+
+    pub(super) fn allows(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::License { id, exception } => {
+                self.allow.contains(id)
+                    && !self.deny.contains(id)
+                    && exception.as_ref().is_none_or(|e| !self.deny.contains(e))
+            }
+            Expression::And(left, right) => self.allows(left) && self.allows(right),
+            Expression::Or(left, right) => self.allows(left) || self.allows(right),
+        }
+    }
+
+    /// Check every declared license against the policy, and compare the sources to each other
+    pub(super) fn evaluate(&self, declared: &[DeclaredLicense]) -> LicenseReport {
+        let parsed: Vec<_> = declared
+            .iter()
+            .map(|d| (d, d.expression.parse::<Expression>().ok()))
+            .collect();
+
+        let acceptable = parsed
+            .iter()
+            .filter(|(_, e)| e.as_ref().is_some_and(|e| self.allows(e)))
+            .count();
+
+        let canonical = |(d, e): &(&DeclaredLicense, Option<Expression>)| match e {
+            Some(e) => e.canonical(),
+            None => d.expression.to_lowercase(),
+        };
+        let mut conflicts = Vec::new();
+        for (i, first) in parsed.iter().enumerate() {
+            for second in &parsed[i + 1..] {
+                if canonical(first) != canonical(second) {
+                    conflicts.push((first.0.clone(), second.0.clone()));
+                }
+            }
+        }
+
+        LicenseReport {
+            acceptable,
+            evaluated: parsed.len(),
+            conflicts,
+        }
+    }
+}
+
+impl LicensePolicy {
+    /// Check the licenses of the dependencies that could be resolved against the policy.
+    /// A license that can't be parsed can't be shown to be allowed, so it counts as incompatible.
+    pub(super) fn check_dependencies(
+        &self,
+        dependencies: &[ResolvedDependency],
+    ) -> DependencyReport {
+        let mut report = DependencyReport::default();
+        for ResolvedDependency { name, expression } in dependencies {
+            let Some(expression) = expression else {
+                continue;
+            };
+            match expression.parse() {
+                Ok(parsed) if self.allows(&parsed) => report.compatible += 1,
+                _ => report.incompatible.push(DependencyLicense {
+                    name: name.clone(),
+                    license: expression.clone(),
+                }),
+            }
+        }
+        report
+    }
+}
+
+/// Result of checking a package's licenses against the policy
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct LicenseReport {
+    acceptable: usize,
+    evaluated: usize,
+    /// pairs of sources that declare different licenses
+    pub(super) conflicts: Vec<(DeclaredLicense, DeclaredLicense)>,
+}
+
+impl LicenseReport {
+    /// Fraction of the declared licenses that are acceptable, 0 when no license was found
+    pub(super) fn score(&self) -> f64 {
+        if self.evaluated == 0 {
+            0.
+        } else {
+            self.acceptable as f64 / self.evaluated as f64
+        }
+    }
+}
+
+/// Result of checking the licenses of a package's dependencies against the policy
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct DependencyReport {
+    compatible: usize,
+    pub(super) incompatible: Vec<DependencyLicense>,
+}
+
+impl DependencyReport {
+    /// Fraction of the resolved dependencies whose license is compatible, 1 when none were resolved
+    pub(super) fn score(&self) -> f64 {
+        let resolved = self.compatible + self.incompatible.len();
+        if resolved == 0 {
+            1.
+        } else {
+            self.compatible as f64 / resolved as f64
+        }
+    }
+}
+
+/// The license a dependency declares, if it could be found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ResolvedDependency {
+    pub(super) name: String,
+    pub(super) expression: Option<String>,
+}
+
+/// Directory holding a local mirror of npm registry documents, one `<name>.json` per package.
+///
+/// Set with `NPM_METADATA_MIRROR`, and consulted for dependencies that aren't in the registry.
+fn get_mirror() -> Option<&'static Path> {
+    static MIRROR: OnceCell<Option<PathBuf>> = OnceCell::new();
+    MIRROR
+        .get_or_init(|| std::env::var_os("NPM_METADATA_MIRROR").map(PathBuf::from))
+        .as_deref()
+}
+
+/// Look up the license each dependency declares, first among the packages already in the
+/// registry and then in the npm metadata mirror
+pub(super) async fn resolve_dependencies<'a, I>(names: I) -> Vec<ResolvedDependency>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let names: Vec<_> = names.into_iter().collect();
+    if names.is_empty() {
+        return Vec::new();
+    }
+
+    let db = database::get_database().await;
+    futures::future::join_all(names.into_iter().map(|name| {
+        let db = &db;
+        async move {
+            let expression = match from_registry(db, name).await {
+                Some(expression) => Some(expression),
+                None => get_mirror().and_then(|mirror| from_mirror(mirror, name)),
+            };
+            ResolvedDependency {
+                name: name.clone(),
+                expression,
+            }
+        }
+    }))
+    .await
+}
+
+async fn from_registry(db: &FirestoreDb, name: &String) -> Option<String> {
+    #[derive(Deserialize, Debug)]
+    struct JustLicense {
+        #[serde(rename = "License", default)]
+        license: Option<String>,
+    }
+
+    let found: Vec<JustLicense> = db
+        .fluent()
+        .select()
+        .fields([database::LICENSE])
+        .from(database::METADATA)
+        .limit(1)
+        .filter(|q| q.field(database::NAME).eq(name))
+        .obj()
+        .query()
+        .await
+        .map_err(|e| log::error!("looking up license of dependency `{}`: {}", name, e))
+        .ok()?;
+
+    found.into_iter().next()?.license
+}
+
+fn from_mirror(mirror: &Path, name: &str) -> Option<String> {
+    #[derive(Deserialize, Debug)]
+    struct Packument {
+        license: Option<PackageJsonLicense>,
+    }
+
+    // names come from uploaded packages, so they mustn't lead out of the mirror
+    if !is_npm_name(name) {
+        log::warn!("not looking up `{}` in npm metadata mirror", name);
+        return None;
+    }
+    // scoped packages (`@scope/name`) end up in a directory per scope
+    let file = fs::File::open(mirror.join(format!("{name}.json"))).ok()?;
+    serde_json::from_reader::<_, Packument>(io::BufReader::new(file))
+        .map_err(|e| log::warn!("reading `{}` from npm metadata mirror: {}", name, e))
+        .ok()?
+        .license?
+        .into_expression()
+}
+
+/// Whether `name` could be a package on npm: a name, after at most one `@scope/`, made of
+/// characters that are safe in URLs and not starting with a dot
+fn is_npm_name(name: &str) -> bool {
+    let (scope, name) = match name.strip_prefix('@').map(|scoped| scoped.split_once('/')) {
+        Some(Some((scope, name))) => (Some(scope), name),
+        Some(None) => return false,
+        None => (None, name),
+    };
+    let valid = |part: &str| {
+        !part.is_empty()
+            && !part.starts_with('.')
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'))
+    };
+    scope.map_or(true, valid) && valid(name)
+}
+
+/// Find the license files at the top of an extracted package and identify what they contain.
+///
+/// Packages are extracted into a single directory (`package/` for npm, `owner-repo-sha/` for
+/// GitHub), so only the first two levels are searched. Several license files (like
+/// `LICENSE-MIT` and `LICENSE-APACHE`) conventionally mean the user can choose between them.
+pub(super) fn from_license_files<P: AsRef<Path>>(path: P) -> Option<DeclaredLicense> {
+    let ids: Vec<_> = WalkDir::new(path)
+        .max_depth(2)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && is_license_file(&e.file_name().to_string_lossy()))
+        .filter_map(|e| fs::read(e.path()).ok())
+        .filter_map(|text| identify_license_text(&String::from_utf8_lossy(&text)))
+        .collect();
+
+    (!ids.is_empty()).then(|| DeclaredLicense {
+        source: LicenseSource::LicenseFile,
+        expression: ids.join(" OR "),
+    })
+}
+
+pub(super) fn is_license_file(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let stem = name.split('.').next().unwrap_or_default();
+    LICENSE_FILE_STEMS
+        .iter()
+        .any(|s| stem == *s || stem.starts_with(&format!("{s}-")))
+}
+
+/// Identify the license in the text of a license file, using an `SPDX-License-Identifier` tag
+/// if there is one, otherwise by looking for phrases from the text of common licenses
+pub(super) fn identify_license_text(text: &str) -> Option<String> {
+    if let Some(tagged) = text
+        .lines()
+        .find_map(|line| line.split_once("SPDX-License-Identifier:"))
+    {
+        return Some(tagged.1.trim().trim_end_matches("*/").trim().to_owned());
+    }
+
+    // license texts are often re-wrapped or re-cased, so compare without either
+    let text = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let has = |phrase: &str| text.contains(&phrase.to_lowercase());
+
+    let id = if has("GNU AFFERO GENERAL PUBLIC LICENSE") {
+        "AGPL-3.0-only"
+    } else if has("GNU LESSER GENERAL PUBLIC LICENSE") {
+        if has("Version 2.1") {
+            "LGPL-2.1-only"
+        } else {
+            "LGPL-3.0-only"
+        }
+    } else if has("GNU GENERAL PUBLIC LICENSE") {
+        if has("Version 2,") {
+            "GPL-2.0-only"
+        } else {
+            "GPL-3.0-only"
+        }
+    } else if has("Apache License") && has("Version 2.0") {
+        "Apache-2.0"
+    } else if has("Mozilla Public License Version 2.0") {
+        "MPL-2.0"
+    } else if has("This is free and unencumbered software released into the public domain") {
+        "Unlicense"
+    } else if has("Permission is hereby granted, free of charge") {
+        "MIT"
+    } else if has("Permission to use, copy, modify, and/or distribute this software") {
+        "ISC"
+    } else if has("Redistribution and use in source and binary forms") {
+        if has("Neither the name") {
+            "BSD-3-Clause"
+        } else {
+            "BSD-2-Clause"
+        }
+    } else {
+        return None;
+    };
+    Some(id.to_owned())
+}
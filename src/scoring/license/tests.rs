@@ -0,0 +1,320 @@
+use super::*;
+
+use crate::queries::types::PackageId;
+
+fn license(id: &str) -> Expression {
+    Expression::License {
+        id: id.to_owned(),
+        exception: None,
+    }
+}
+
+fn declared(source: LicenseSource, expression: &str) -> DeclaredLicense {
+    DeclaredLicense {
+        source,
+        expression: expression.to_owned(),
+    }
+}
+
+#[test]
+fn parse_single() {
+    assert_eq!("MIT".parse(), Ok(license("mit")));
+    assert_eq!("GPL-2.0+".parse(), Ok(license("gpl-2.0-or-later")));
+    assert_eq!("GPL-3.0".parse(), Ok(license("gpl-3.0-only")));
+    assert_eq!(
+        "LGPL-2.1-or-later".parse(),
+        Ok(license("lgpl-2.1-or-later"))
+    );
+}
+
+#[test]
+fn parse_compound() {
+    assert_eq!(
+        "MIT OR Apache-2.0".parse(),
+        Ok(Expression::Or(
+            Box::new(license("mit")),
+            Box::new(license("apache-2.0"))
+        ))
+    );
+
+    // AND binds tighter than OR
+    assert_eq!(
+        "MIT OR ISC AND Zlib".parse(),
+        Ok(Expression::Or(
+            Box::new(license("mit")),
+            Box::new(Expression::And(
+                Box::new(license("isc")),
+                Box::new(license("zlib"))
+            ))
+        ))
+    );
+
+    assert_eq!(
+        "(MIT OR ISC) AND GPL-2.0-only WITH Classpath-exception-2.0".parse(),
+        Ok(Expression::And(
+            Box::new(Expression::Or(
+                Box::new(license("mit")),
+                Box::new(license("isc"))
+            )),
+            Box::new(Expression::License {
+                id: "gpl-2.0-only".to_owned(),
+                exception: Some("classpath-exception-2.0".to_owned()),
+            })
+        ))
+    );
+}
+
+#[test]
+fn parse_invalid() {
+    for expression in [
+        "", "MIT OR", "(MIT", "MIT)", "AND MIT", "MIT ISC", "MIT WITH",
+    ] {
+        assert_eq!(
+            expression.parse::<Expression>(),
+            Err(ParseError),
+            "{}",
+            expression
+        );
+    }
+}
+
+#[test]
+fn policy_allow_and_deny() {
+    let policy = LicensePolicy::new(["MIT", "Apache-2.0", "GPL-2.0+"], ["GPL-3.0"]);
+
+    let allows = |e: &str| policy.allows(&e.parse().unwrap());
+    assert!(allows("MIT"));
+    assert!(allows("gpl-2.0-or-later"));
+    assert!(allows("MIT OR GPL-3.0-only"));
+    assert!(!allows("MIT AND GPL-3.0-only"));
+    assert!(!allows("ISC"));
+    assert!(!allows("GPL-3.0"));
+}
+
+#[test]
+fn policy_deny_exception() {
+    let policy = LicensePolicy::new(["GPL-2.0-only"], ["Classpath-exception-2.0"]);
+    assert!(policy.allows(&"GPL-2.0-only".parse().unwrap()));
+    assert!(!policy.allows(&"GPL-2.0-only WITH Classpath-exception-2.0".parse().unwrap()));
+}
+
+#[test]
+fn default_policy() {
+    let policy = LicensePolicy::new(DEFAULT_ALLOWED, [] as [&str; 0]);
+    assert!(policy.allows(&"MIT".parse().unwrap()));
+    assert!(policy.allows(&"AGPL-3.0-only".parse().unwrap()));
+    assert!(!policy.allows(&"UNLICENSED".parse().unwrap()));
+}
+
+#[test]
+fn evaluate_agreeing_sources() {
+    let policy = LicensePolicy::new(["MIT", "Apache-2.0"], [] as [&str; 0]);
+    let report = policy.evaluate(&[
+        declared(LicenseSource::PackageJson, "(MIT OR Apache-2.0)"),
+        declared(LicenseSource::LicenseFile, "Apache-2.0 OR MIT"),
+    ]);
+    assert_eq!(
+        report,
+        LicenseReport {
+            acceptable: 2,
+            evaluated: 2,
+            conflicts: vec![],
+        }
+    );
+    assert_eq!(report.score(), 1.);
+}
+
+#[test]
+fn evaluate_conflicting_sources() {
+    let policy = LicensePolicy::new(["MIT"], [] as [&str; 0]);
+    let package_json = declared(LicenseSource::PackageJson, "MIT");
+    let github = declared(LicenseSource::Github, "GPL-3.0");
+    let report = policy.evaluate(&[package_json.clone(), github.clone()]);
+    assert_eq!(
+        report,
+        LicenseReport {
+            acceptable: 1,
+            evaluated: 2,
+            conflicts: vec![(package_json.clone(), github.clone())],
+        }
+    );
+    assert_eq!(report.score(), 0.5);
+    assert_eq!(
+        LicenseConflict::from((package_json, github)),
+        LicenseConflict {
+            source: "package.json".to_owned(),
+            license: "MIT".to_owned(),
+            other_source: "GitHub".to_owned(),
+            other_license: "GPL-3.0".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn evaluate_nothing_declared() {
+    let report = LicensePolicy::new(["MIT"], [] as [&str; 0]).evaluate(&[]);
+    assert_eq!(report.score(), 0.);
+}
+
+#[test]
+fn license_file_names() {
+    assert!(is_license_file("LICENSE"));
+    assert!(is_license_file("license.md"));
+    assert!(is_license_file("LICENCE.txt"));
+    assert!(is_license_file("LICENSE-MIT"));
+    assert!(is_license_file("COPYING"));
+    assert!(!is_license_file("licenses.json"));
+    assert!(!is_license_file("index.js"));
+}
+
+#[test]
+fn identify_texts() {
+    let mit = "MIT License\n\nPermission is hereby granted, free of charge, to any person\n";
+    assert_eq!(identify_license_text(mit).as_deref(), Some("MIT"));
+
+    let tagged = "/* SPDX-License-Identifier: MIT OR Apache-2.0 */";
+    assert_eq!(
+        identify_license_text(tagged).as_deref(),
+        Some("MIT OR Apache-2.0")
+    );
+
+    let gpl2 = "GNU GENERAL PUBLIC LICENSE\n Version 2, June 1991";
+    assert_eq!(identify_license_text(gpl2).as_deref(), Some("GPL-2.0-only"));
+
+    assert_eq!(identify_license_text("All rights reserved."), None);
+}
+
+#[test]
+fn license_files_in_package() {
+    let path = std::env::temp_dir().join(PackageId::new().as_ref());
+    fs::create_dir_all(path.join("package/node_modules/dep")).unwrap();
+    fs::write(
+        path.join("package/LICENSE-MIT"),
+        "Permission is hereby granted, free of charge",
+    )
+    .unwrap();
+    fs::write(
+        path.join("package/LICENSE-APACHE"),
+        "Apache License\nVersion 2.0, January 2004",
+    )
+    .unwrap();
+    // too deep to be the package's license
+    fs::write(path.join("package/node_modules/dep/LICENSE"), "ISC").unwrap();
+
+    let found = from_license_files(&path).unwrap();
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(found.source, LicenseSource::LicenseFile);
+    let expression: Expression = found.expression.parse().unwrap();
+    assert_eq!(expression.canonical(), "(apache-2.0 or mit)");
+}
+
+#[test]
+fn package_json_license_forms() {
+    let expression = |data| {
+        serde_json::from_str::<PackageJsonLicense>(data)
+            .unwrap()
+            .into_expression()
+    };
+
+    assert_eq!(
+        expression(r#""(MIT OR Apache-2.0)""#).as_deref(),
+        Some("(MIT OR Apache-2.0)")
+    );
+    assert_eq!(
+        expression(r#"{"type": "ISC", "url": "https://opensource.org/licenses/ISC"}"#).as_deref(),
+        Some("ISC")
+    );
+    assert_eq!(expression(r#""SEE LICENSE IN LICENSE.txt""#), None);
+    assert_eq!(expression(r#"{"name": "MIT"}"#), None);
+    assert_eq!(expression(r#"[{"type": "MIT"}]"#), None);
+}
+
+fn resolved(name: &str, expression: Option<&str>) -> ResolvedDependency {
+    ResolvedDependency {
+        name: name.to_owned(),
+        expression: expression.map(str::to_owned),
+    }
+}
+
+#[test]
+fn check_dependency_licenses() {
+    let policy = LicensePolicy::new(["MIT", "ISC"], ["GPL-3.0-only"]);
+    let report = policy.check_dependencies(&[
+        resolved("chalk", Some("MIT")),
+        resolved("glob", Some("ISC")),
+        resolved("readline-sync", Some("GPL-3.0")),
+        resolved("left-pad", Some("WTFPL")),
+        resolved("not-found", None),
+        resolved("odd", Some("MIT OR")),
+    ]);
+
+    assert_eq!(
+        report,
+        DependencyReport {
+            compatible: 2,
+            incompatible: vec![
+                DependencyLicense {
+                    name: "readline-sync".to_owned(),
+                    license: "GPL-3.0".to_owned(),
+                },
+                DependencyLicense {
+                    name: "left-pad".to_owned(),
+                    license: "WTFPL".to_owned(),
+                },
+                DependencyLicense {
+                    name: "odd".to_owned(),
+                    license: "MIT OR".to_owned(),
+                },
+            ],
+        }
+    );
+    assert_eq!(report.score(), 0.4);
+}
+
+#[test]
+fn unresolved_dependencies_not_penalized() {
+    let policy = LicensePolicy::new(["MIT"], [] as [&str; 0]);
+    let report = policy.check_dependencies(&[resolved("not-found", None)]);
+    assert_eq!(report.score(), 1.);
+}
+
+#[test]
+fn dependency_license_from_mirror() {
+    let mirror = std::env::temp_dir().join(PackageId::new().as_ref());
+    fs::create_dir_all(mirror.join("@babel")).unwrap();
+    fs::write(
+        mirror.join("chalk.json"),
+        r#"{"name": "chalk", "license": "MIT", "versions": {}}"#,
+    )
+    .unwrap();
+    fs::write(
+        mirror.join("@babel/core.json"),
+        r#"{"name": "@babel/core", "license": {"type": "MIT"}}"#,
+    )
+    .unwrap();
+
+    let chalk = from_mirror(&mirror, "chalk");
+    let babel = from_mirror(&mirror, "@babel/core");
+    let missing = from_mirror(&mirror, "missing");
+    fs::remove_dir_all(&mirror).unwrap();
+
+    assert_eq!(chalk.as_deref(), Some("MIT"));
+    assert_eq!(babel.as_deref(), Some("MIT"));
+    assert_eq!(missing, None);
+}
+
+#[test]
+fn npm_names() {
+    assert!(is_npm_name("chalk"));
+    assert!(is_npm_name("@babel/core"));
+    assert!(is_npm_name("lodash.merge"));
+    assert!(!is_npm_name("../../etc/x"));
+    assert!(!is_npm_name("@scope/../x"));
+    assert!(!is_npm_name("@a/b/c"));
+    assert!(!is_npm_name("/etc/passwd"));
+    assert!(!is_npm_name("..\\x"));
+    assert!(!is_npm_name(".hidden"));
+    assert!(!is_npm_name("@scope"));
+    assert!(!is_npm_name(""));
+}
@@ -1,17 +1,47 @@
+#[cfg(test)]
+mod tests;
+
+mod documentation;
+mod git;
 mod github;
+mod gitlab;
+mod host;
+mod integrity;
+mod license;
 mod path;
+mod readme;
+mod test_suite;
+mod upstream;
 mod url;
 mod version;
 
-use self::url::{get_client, NpmAbbrMetadata, NpmDist, NpmDistTags, NpmVersion, UrlKind};
-use crate::queries::types::{PackageData, PackageId, PackageRating};
+use self::documentation::Documentation;
+use self::host::{Downloaded, RepositoryHost, Snapshot};
+use self::license::{DeclaredLicense, LicensePolicy, ResolvedDependency};
+use self::readme::Readme;
+use self::test_suite::TestSuite;
+use self::url::{get_client, GithubUrl, NpmAbbrMetadata, NpmVersion, RepositoryUrl, UrlKind};
+use crate::{
+    cache,
+    queries::types::{PackageData, PackageId, PackageRating},
+};
+
+pub use self::version::registry_requirement;
 
 use base64::{engine::general_purpose, read::DecoderReader};
 use libflate::gzip;
 use semver::Version;
-use std::io::{self, Read};
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    time::Duration,
+};
 use zip::ZipArchive;
 
+/// Version of the way ratings are computed, kept with each rating in the history.
+/// Increase it whenever a change to scoring makes new ratings incomparable with older ones.
+pub const POLICY_VERSION: u32 = 1;
+
 #[derive(thiserror::Error, Debug)]
 pub enum RatingError {
     #[error("did not find a package.json")]
@@ -20,11 +50,30 @@ pub enum RatingError {
     MissingRepository,
     #[error("npm api response did not contain tarball for latest version")]
     CouldNotGetLatestVersion,
+    #[error("package `{name}` has no version or dist-tag `{requested}`")]
+    NpmVersionNotFound { name: String, requested: String },
+    #[error("tarball `{tarball}` does not match its digest `{expected}`")]
+    IntegrityMismatch { tarball: String, expected: String },
+    #[error("repository `{repository}` does not exist or is private")]
+    RepositoryNotFound { repository: String },
+    #[error("repository `{repository}` has no tag, branch or commit `{reference}`")]
+    GithubRefNotFound {
+        repository: String,
+        reference: String,
+    },
     #[error("could not convert repository url: `{0}`")]
     UrlParseError(String),
+    #[error("`{field}` can't be given along with `{url}`")]
+    FieldNotApplicable { field: &'static str, url: String },
+    #[error("`{0}` is not a host repositories can be cloned from")]
+    HostNotAllowed(String),
     #[error("{0}")]
     GraphQlError(#[from] github::GraphQlError),
     #[error("{0}")]
+    GitlabError(gitlab::GitlabError),
+    #[error("{0}")]
+    GitError(git::GitError),
+    #[error("{0}")]
     ZipError(#[from] zip::result::ZipError),
     #[error("{0}")]
     IoError(#[from] io::Error),
@@ -37,20 +86,99 @@ pub enum RatingError {
 }
 use RatingError::*;
 
+impl RatingError {
+    /// Name of the kind of error, for reporting it in a structured way
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MissingPackageJson => "MissingPackageJson",
+            MissingRepository => "MissingRepository",
+            CouldNotGetLatestVersion => "CouldNotGetLatestVersion",
+            NpmVersionNotFound { .. } => "NpmVersionNotFound",
+            GithubRefNotFound { .. } => "GithubRefNotFound",
+            RepositoryNotFound { .. } => "RepositoryNotFound",
+            IntegrityMismatch { .. } => "IntegrityMismatch",
+            UrlParseError(_) => "UrlParseError",
+            FieldNotApplicable { .. } => "FieldNotApplicable",
+            HostNotAllowed(_) => "HostNotAllowed",
+            GraphQlError(_) => "GraphQlError",
+            GitlabError(_) => "GitlabError",
+            GitError(_) => "GitError",
+            ZipError(_) => "ZipError",
+            IoError(_) => "IoError",
+            Base64Error(_) => "Base64Error",
+            DeserializeError(_) => "DeserializeError",
+            ReqwestError(_) => "ReqwestError",
+        }
+    }
+
+    /// How long to wait before trying again, if scoring failed because GitHub's rate limit ran out
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            GraphQlError(github::GraphQlError::RateLimited { retry_after }) => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
+impl From<git::GitError> for RatingError {
+    fn from(e: git::GitError) -> Self {
+        match e {
+            git::GitError::InternalAddress(host) => HostNotAllowed(host),
+            git::GitError::NotFound(repository) => RepositoryNotFound { repository },
+            e => GitError(e),
+        }
+    }
+}
+
+impl From<gitlab::GitlabError> for RatingError {
+    fn from(e: gitlab::GitlabError) -> Self {
+        match e {
+            gitlab::GitlabError::ProjectNotFound(repository) => RepositoryNotFound { repository },
+            e => GitlabError(e),
+        }
+    }
+}
+
 type RatingResult<T> = Result<T, RatingError>;
 
 pub struct RatedPackage {
     pub name: String,
     pub version: Version,
     pub id: PackageId,
+    /// SPDX expression the package declares
+    pub license: Option<String>,
+    /// the `dependencies` of its `package.json`, names to version requirements
+    pub dependencies: HashMap<String, String>,
     pub rating: PackageRating,
+    /// the package as a zip, still base64-encoded if it was uploaded that way
     pub content: Vec<u8>,
+    /// bytes of the zip once decoded
+    pub content_size: u64,
+    /// commit of the repository the package was downloaded at
+    pub commit: Option<String>,
+    /// SRI digest the package's registry tarball was verified against
+    pub integrity: Option<String>,
+    /// the tarball as the registry served it, for packages from npm
+    pub tarball: Option<Vec<u8>>,
+}
+
+/// What was found out about a package from its extracted contents
+struct PathRating {
+    name: String,
+    version: Version,
+    license: Option<String>,
+    dependencies: HashMap<String, String>,
+    rating: PackageRating,
 }
 
 pub async fn rate_package(package: PackageData) -> RatingResult<RatedPackage> {
     match package {
         PackageData::Content { content } => Ok(from_content(content.into_bytes()).await?),
-        PackageData::Url { url } => from_url(&url).await,
+        PackageData::Url {
+            url,
+            version,
+            reference,
+        } => from_url(&url, version.as_deref(), reference.as_deref()).await,
     }
 }
 
@@ -59,6 +187,7 @@ async fn from_content(content: Vec<u8>) -> RatingResult<RatedPackage> {
     DecoderReader::new(content.as_slice(), &general_purpose::STANDARD)
         .read_to_end(&mut buf)
         .map_err(Base64Error)?;
+    let content_size = buf.len() as u64;
     let buf = io::Cursor::new(buf);
 
     let id = PackageId::new();
@@ -69,30 +198,54 @@ async fn from_content(content: Vec<u8>) -> RatingResult<RatedPackage> {
     let _ = std::fs::remove_dir_all(&path)
         .map_err(|e| log::error!("Error removing files after scoring: `{}`", e));
 
-    let (name, version, rating) = result?;
+    let PathRating {
+        name,
+        version,
+        license,
+        dependencies,
+        rating,
+    } = result?;
     Ok(RatedPackage {
         name,
         version,
         id,
+        license,
+        dependencies,
         rating,
         content,
+        content_size,
+        commit: None,
+        integrity: None,
+        tarball: None,
     })
 }
 
 // to catch errors and still remove temporary files if so
-async fn from_content_internal(
-    buf: io::Cursor<Vec<u8>>,
-    path: &str,
-) -> RatingResult<(String, Version, PackageRating)> {
-    ZipArchive::new(buf)?.extract(path)?;
-    path::rating_from_path(path).await
+async fn from_content_internal(buf: io::Cursor<Vec<u8>>, path: &str) -> RatingResult<PathRating> {
+    if is_gzip(buf.get_ref()) {
+        tar::Archive::new(gzip::Decoder::new(buf)?).unpack(path)?;
+    } else {
+        ZipArchive::new(buf)?.extract(path)?;
+    }
+    path::rating_from_path(path, None).await
+}
+
+/// Whether an archive is a gzipped tarball, like npm's, rather than a zip
+pub fn is_gzip(content: &[u8]) -> bool {
+    content.starts_with(&[0x1f, 0x8b])
 }
 
-async fn from_url(url: &str) -> RatingResult<RatedPackage> {
+/// `version` picks the version or dist-tag of an npm package, and `reference` the tag, branch or
+/// commit of a GitHub repository, in place of one in the URL
+async fn from_url(
+    url: &str,
+    version: Option<&str>,
+    reference: Option<&str>,
+) -> RatingResult<RatedPackage> {
     let id = PackageId::new();
     let path = format!("/tmp/{}", id.as_ref());
 
-    let result = from_url_internal(url, &path, id).await;
+    let result = from_url_internal(url, version, reference, &path, id).await;
 
     let _ = std::fs::remove_dir_all(&path)
         .map_err(|e| log::error!("Error removing files after scoring: `{}`", e));
@@ -102,98 +255,290 @@ async fn from_url(url: &str) -> RatingResult<RatedPackage> {
 
 // TODO: content should be base64 encoded before returning
 // to catch errors and still remove temporary files if so
-async fn from_url_internal(url: &str, path: &str, id: PackageId) -> RatingResult<RatedPackage> {
-    let url = url.try_into().map_err(|_| UrlParseError(url.to_string()))?;
+async fn from_url_internal(
+    url: &str,
+    version: Option<&str>,
+    reference: Option<&str>,
+    path: &str,
+    id: PackageId,
+) -> RatingResult<RatedPackage> {
+    let not_applicable = |field| FieldNotApplicable {
+        field,
+        url: url.to_owned(),
+    };
+    let mut url = url.try_into().map_err(|_| UrlParseError(url.to_string()))?;
+    // rather than rate some other revision than the one asked for
+    match (&mut url, version, reference) {
+        (_, None, None) => (),
+        (UrlKind::Npm(npm), Some(version), None) => npm.version = Some(version.to_owned()),
+        (UrlKind::Github(github), None, Some(reference)) => {
+            github.reference = Some(reference.to_owned())
+        }
+        (UrlKind::Npm(_), _, Some(_)) => return Err(not_applicable("Ref")),
+        (_, Some(_), _) => return Err(not_applicable("Version")),
+        (_, None, Some(_)) => return Err(not_applicable("Ref")),
+    }
 
-    let content = match url {
+    let (snapshot, repository, integrity, tarball) = match url {
         UrlKind::Github(url) => {
-            let content = get_client()
-                .get(format!(
-                    "https://api.github.com/repos/{}/{}/zipball",
-                    url.owner, url.name
-                ))
-                .header("X-GitHub-Api-Version", "2022-11-28")
-                .send()
-                .await?
-                .bytes()
-                .await?;
-
-            ZipArchive::new(io::Cursor::new(&content[..]))
-                .unwrap()
-                .extract(path)
-                .unwrap();
-            content.into()
+            let snapshot = url.download(path.as_ref()).await?;
+            // rated at the commit that was downloaded, not wherever the branch is by then
+            let repository = RepositoryUrl::Github(GithubUrl {
+                reference: snapshot.commit.clone(),
+                reference_with_path: false,
+                ..url
+            });
+            (snapshot, Some(repository), None, None)
         }
+        UrlKind::Gitlab(url) => (
+            url.download(path.as_ref()).await?,
+            Some(RepositoryUrl::Gitlab(url)),
+            None,
+            None,
+        ),
+        UrlKind::Git(repository) => (
+            repository.download(path.as_ref()).await?,
+            Some(RepositoryUrl::Git(repository)),
+            None,
+            None,
+        ),
         UrlKind::Npm(url) => {
             let client = get_client();
-            let NpmAbbrMetadata {
-                dist_tags: NpmDistTags { latest },
-                versions,
-            } = client
-                .get(format!("https://registry.npmjs.org/{}", url.name))
-                .header("Accept", "application/vnd.npm.install-v1+json")
+            let metadata = npm_metadata(&url.name).await?;
+
+            let NpmVersion { dist } =
+                metadata
+                    .resolve(url.version.as_deref())
+                    .ok_or_else(|| match url.version {
+                        Some(requested) => NpmVersionNotFound {
+                            name: url.name.clone(),
+                            requested,
+                        },
+                        None => CouldNotGetLatestVersion,
+                    })?;
+
+            let tar_gz = client
+                .get(&dist.tarball)
                 .send()
                 .await?
-                .json()
+                .error_for_status()?
+                .bytes()
                 .await?;
-
-            let NpmVersion {
-                dist: NpmDist { tarball },
-            } = versions
-                .get(&latest)
-                .ok_or_else(|| CouldNotGetLatestVersion)?;
-
-            let tar_gz = client.get(tarball).send().await?.bytes().await?;
+            // checked before anything in it is unpacked
+            let integrity = integrity::verify(dist, &tar_gz)?;
             tar::Archive::new(gzip::Decoder::new(&tar_gz[..])?).unpack(path)?;
 
             let mut content = Vec::new();
             path::zip_dir(path, io::Cursor::new(&mut content))?;
-            content
+            let snapshot = Snapshot {
+                content,
+                commit: None,
+                metrics: None,
+            };
+            (snapshot, None, integrity, Some(tar_gz.into()))
         }
     };
+    let Snapshot {
+        content,
+        commit,
+        metrics,
+    } = snapshot;
+    let downloaded = repository.map(|repository| Downloaded {
+        repository,
+        metrics,
+    });
 
-    let (name, version, rating) = path::rating_from_path(&path).await?;
+    let PathRating {
+        name,
+        version,
+        license,
+        dependencies,
+        rating,
+    } = path::rating_from_path(&path, downloaded).await?;
     Ok(RatedPackage {
         name,
         version,
         id,
+        license,
+        dependencies,
         rating,
+        content_size: content.len() as u64,
         content,
+        commit,
+        integrity,
+        tarball,
     })
 }
 
+/// Abbreviated metadata of a package from the npm registry, or the copy cached for it
+async fn npm_metadata(name: &str) -> RatingResult<NpmAbbrMetadata> {
+    let key = format!("npm/{}", name);
+    if let Some(text) = cache::get().get(&key) {
+        return Ok(serde_json::from_str(&text)?);
+    }
+
+    let text = get_client()
+        .get(upstream::get().npm_package(name))
+        .header("Accept", "application/vnd.npm.install-v1+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let metadata = serde_json::from_str(&text)?;
+    cache::get().insert(&key, text);
+    Ok(metadata)
+}
+
 struct ScoringData {
-    readme_exists: bool,
-    documentation_exists: bool,
+    readme: Option<Readme>,
+    documentation: Option<Documentation>,
+    issues_closed: usize,
+    issues_total: usize,
+    checks: CheckResults,
+    test_suite: Option<TestSuite>,
+    /// number of commits made by each author
+    commit_counts: Vec<usize>,
+    response_times: Option<ResponseTimes>,
+    /// how long ago the default branch was last committed to, for hosts without issues to judge
+    /// responsiveness by
+    days_since_commit: Option<f64>,
+    /// licenses declared by the package and its repository, which may disagree
+    licenses: Vec<DeclaredLicense>,
+    dependency_licenses: Vec<ResolvedDependency>,
+}
+
+/// Find the smallest number of authors who together made at least half of the commits, and
+/// scale it to [0, 1] so that a single author is 0 and more authors approach 1
+fn bus_factor(mut commit_counts: Vec<usize>) -> f64 {
+    commit_counts.sort_unstable_by(|a, b| b.cmp(a));
+    let total: usize = commit_counts.iter().sum();
+    if total == 0 {
+        return 0.;
+    }
+
+    let mut authors = 0;
+    let mut commits = 0;
+    for count in commit_counts {
+        authors += 1;
+        commits += count;
+        if commits * 2 >= total {
+            break;
+        }
+    }
+
+    1. - 1. / authors as f64
+}
+
+/// Outcomes of the check suites (CI) run on the latest commit of the default branch
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CheckResults {
+    passed: usize,
+    failed: usize,
+}
+
+impl CheckResults {
+    /// Fraction of finished check suites that passed, if any have finished
+    fn pass_rate(&self) -> Option<f64> {
+        let total = self.passed + self.failed;
+        (total > 0).then(|| self.passed as f64 / total as f64)
+    }
+}
+
+/// Combine the signals for the Correctness metric.
+///
+/// A repository without issues or without CI has no evidence either way for that part, so the
+/// remaining parts are weighted up instead of counting the missing part as a failure.
+fn correctness(
     issues_closed: usize,
     issues_total: usize,
-    num_contributors: usize,
-    weeks_since_last_issue: f64,
-    license_correct: bool,
+    checks: &CheckResults,
+    test_suite: Option<&TestSuite>,
+) -> f64 {
+    let issues = (issues_total > 0).then(|| issues_closed as f64 / issues_total as f64);
+    // relative weights of each part
+    let parts = [
+        (4., issues),
+        (3., test_suite.map(TestSuite::score)),
+        (3., checks.pass_rate()),
+    ];
+
+    let (weight, total) = parts
+        .iter()
+        .filter_map(|(weight, part)| part.map(|p| (weight, p)))
+        .fold((0., 0.), |(weights, total), (weight, part)| {
+            (weights + weight, total + weight * part)
+        });
+
+    if weight == 0. {
+        0.
+    } else {
+        (total / weight).clamp(0., 1.)
+    }
+}
+
+/// Medians over the most recent issues and pull requests of a repository
+#[derive(Debug, PartialEq)]
+struct ResponseTimes {
+    median_days_to_first_response: f64,
+    median_days_to_close: f64,
+}
+
+/// Number of days waiting for a first response after which ResponsiveMaintainer is halved
+const FIRST_RESPONSE_HALF_LIFE_DAYS: f64 = 7.;
+/// Number of days waiting for an issue to be closed after which ResponsiveMaintainer is halved
+const CLOSE_HALF_LIFE_DAYS: f64 = 30.;
+/// Number of days since the last commit after which ResponsiveMaintainer is halved, when there
+/// are no issues to go by
+const COMMIT_HALF_LIFE_DAYS: f64 = 90.;
+
+impl ResponseTimes {
+    fn score(&self) -> f64 {
+        let decay = |days: f64, half_life: f64| 0.5_f64.powf(days / half_life);
+        let first_response = decay(
+            self.median_days_to_first_response,
+            FIRST_RESPONSE_HALF_LIFE_DAYS,
+        );
+        let close = decay(self.median_days_to_close, CLOSE_HALF_LIFE_DAYS);
+        (first_response + close) / 2.
+    }
 }
 
 impl From<(ScoringData, f64, f64)> for PackageRating {
     fn from(
         (
             ScoringData {
-                readme_exists,
-                documentation_exists,
+                readme,
+                documentation,
                 issues_closed,
                 issues_total,
-                num_contributors,
-                weeks_since_last_issue,
-                license_correct,
+                checks,
+                test_suite,
+                commit_counts,
+                response_times,
+                days_since_commit,
+                licenses,
+                dependency_licenses,
             },
             good_pinning_practice,
             pull_request,
         ): (ScoringData, f64, f64),
     ) -> Self {
-        let bus_factor = 1. - (1. / num_contributors.max(1) as f64);
-        let correctness = (issues_closed as f64 / issues_total as f64).max(0.).min(1.);
-        let ramp_up =
-            if readme_exists { 0.5 } else { 0. } + if documentation_exists { 0.5 } else { 0. };
-        let responsive_maintainer = (1. / weeks_since_last_issue).max(0.).min(1.);
-        let license_score = if license_correct { 1. } else { 0. };
+        let bus_factor = bus_factor(commit_counts);
+        let correctness = correctness(issues_closed, issues_total, &checks, test_suite.as_ref());
+        let ramp_up = readme.map_or(0., |r| r.readability()) * 0.5
+            + documentation.map_or(0., |d| d.score()) * 0.5;
+        // with no recent issues or pull requests there is nothing to judge either way, unless
+        // recent commits show the package is still looked after
+        let responsive_maintainer = response_times
+            .map(|r| r.score())
+            .or(days_since_commit.map(|days| 0.5_f64.powf(days / COMMIT_HALF_LIFE_DAYS)))
+            .unwrap_or(0.5);
+        let license = LicensePolicy::get().evaluate(&licenses);
+        let dependencies = LicensePolicy::get().check_dependencies(&dependency_licenses);
+        // a package is only as usable as the most restrictive license it pulls in
+        let license_score = license.score() * dependencies.score();
+        let license_conflicts = license.conflicts.into_iter().map(Into::into).collect();
 
         PackageRating {
             bus_factor,
@@ -204,6 +549,8 @@ impl From<(ScoringData, f64, f64)> for PackageRating {
             good_pinning_practice,
             pull_request,
             net_score: 0.,
+            incompatible_licenses: dependencies.incompatible,
+            license_conflicts,
         }
         .set_net_score()
     }
@@ -2,15 +2,18 @@
 mod tests;
 
 use super::{
-    github,
-    url::{canonicalize_repo, GithubUrl},
-    version,
+    documentation::Documentation,
+    host::{Downloaded, RepositoryHost},
+    license::{self, DeclaredLicense, LicenseSource, PackageJsonLicense},
+    readme::{self, Readme},
+    test_suite::TestSuite,
+    url::{canonicalize_repo, RepositoryUrl},
+    version::{self, DependencySections},
+    PathRating,
     RatingError::{self, *},
     RatingResult, ScoringData,
 };
-use crate::queries::types::PackageRating;
 
-use git_url_parse::GitUrl;
 use semver::Version;
 use serde::Deserialize;
 use std::{
@@ -35,18 +38,45 @@ enum PackageJson {
         version: Version,
         repository: Repository,
         dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "devDependencies")]
+        dev_dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "peerDependencies")]
+        peer_dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "optionalDependencies")]
+        optional_dependencies: Option<HashMap<String, String>>,
+        homepage: Option<String>,
+        scripts: Option<HashMap<String, String>>,
+        license: Option<PackageJsonLicense>,
     },
     Flat {
         name: String,
         version: Version,
         repository: String,
         dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "devDependencies")]
+        dev_dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "peerDependencies")]
+        peer_dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "optionalDependencies")]
+        optional_dependencies: Option<HashMap<String, String>>,
+        homepage: Option<String>,
+        scripts: Option<HashMap<String, String>>,
+        license: Option<PackageJsonLicense>,
     },
     #[allow(dead_code)]
     NoRepo {
         name: String,
         version: Version,
         dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "devDependencies")]
+        dev_dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "peerDependencies")]
+        peer_dependencies: Option<HashMap<String, String>>,
+        #[serde(rename = "optionalDependencies")]
+        optional_dependencies: Option<HashMap<String, String>>,
+        homepage: Option<String>,
+        scripts: Option<HashMap<String, String>>,
+        license: Option<PackageJsonLicense>,
     },
 }
 
@@ -54,8 +84,14 @@ enum PackageJson {
 struct PackageJsonVerified {
     name: String,
     version: Version,
-    url: GithubUrl,
+    url: RepositoryUrl,
     dependencies: HashMap<String, String>,
+    dev_dependencies: HashMap<String, String>,
+    peer_dependencies: HashMap<String, String>,
+    optional_dependencies: HashMap<String, String>,
+    homepage: Option<String>,
+    scripts: HashMap<String, String>,
+    license: Option<PackageJsonLicense>,
 }
 
 impl TryFrom<PackageJson> for PackageJsonVerified {
@@ -68,6 +104,12 @@ impl TryFrom<PackageJson> for PackageJsonVerified {
                 version,
                 repository,
                 dependencies,
+                dev_dependencies,
+                peer_dependencies,
+                optional_dependencies,
+                homepage,
+                scripts,
+                license,
             } => Ok(PackageJsonVerified {
                 name,
                 version,
@@ -76,40 +118,64 @@ impl TryFrom<PackageJson> for PackageJsonVerified {
                     Some(map) => map,
                     None => HashMap::new(),
                 },
+                dev_dependencies: dev_dependencies.unwrap_or_default(),
+                peer_dependencies: peer_dependencies.unwrap_or_default(),
+                optional_dependencies: optional_dependencies.unwrap_or_default(),
+                homepage,
+                scripts: scripts.unwrap_or_default(),
+                license,
             }),
             PackageJson::Deep {
                 name,
                 version,
                 repository: Repository { url },
                 dependencies,
+                dev_dependencies,
+                peer_dependencies,
+                optional_dependencies,
+                homepage,
+                scripts,
+                license,
             } => Ok(PackageJsonVerified {
                 name,
                 version,
-                url: GitUrl::parse(&url.trim_start_matches("git+"))
-                    .try_into()
-                    .map_err(|_| UrlParseError(url))?,
+                url: canonicalize_repo(&url)?,
                 dependencies: match dependencies {
                     Some(map) => map,
                     None => HashMap::new(),
                 },
+                dev_dependencies: dev_dependencies.unwrap_or_default(),
+                peer_dependencies: peer_dependencies.unwrap_or_default(),
+                optional_dependencies: optional_dependencies.unwrap_or_default(),
+                homepage,
+                scripts: scripts.unwrap_or_default(),
+                license,
             }),
         }
     }
 }
 
+/// `downloaded` is the repository the package was downloaded from. When the `package.json` links
+/// to the same repository, its metrics are taken at the downloaded commit.
 pub(super) async fn rating_from_path<P: AsRef<Path>>(
     path: P,
-) -> RatingResult<(String, Version, PackageRating)> {
-    let readme_exists = find_file(&path, |name| {
-        name.eq_ignore_ascii_case("readme") || name.eq_ignore_ascii_case("readme.md")
-    })
-    .is_some();
+    downloaded: Option<Downloaded>,
+) -> RatingResult<PathRating> {
+    let readme = find_file(&path, |name| name.to_str().is_some_and(readme::is_readme))
+        .and_then(|entry| fs::read(entry.path()).ok())
+        .map(|text| Readme::new(&String::from_utf8_lossy(&text)));
 
     let PackageJsonVerified {
         name,
         version,
         url,
         dependencies,
+        dev_dependencies,
+        peer_dependencies,
+        optional_dependencies,
+        homepage,
+        scripts,
+        license,
     } = serde_json::from_reader::<_, PackageJson>(io::BufReader::new(fs::File::open(
         find_file(&path, |name| name == "package.json")
             .ok_or_else(|| MissingPackageJson)?
@@ -117,18 +183,63 @@ pub(super) async fn rating_from_path<P: AsRef<Path>>(
     )?))?
     .try_into()?;
 
+    let scoring_data = match downloaded {
+        Some(Downloaded {
+            repository,
+            metrics,
+        }) if repository.same_repository(&url) => match metrics {
+            Some(metrics) => metrics,
+            None => repository.metrics().await?,
+        },
+        _ => url.metrics().await?,
+    };
+    let licenses: Vec<_> = license
+        .and_then(PackageJsonLicense::into_expression)
+        .map(|expression| DeclaredLicense {
+            source: LicenseSource::PackageJson,
+            expression,
+        })
+        .into_iter()
+        .chain(license::from_license_files(&path))
+        .chain(scoring_data.licenses)
+        .collect();
+    // the package's own declaration is what packages depending on it will be checked against
+    let license = licenses.first().map(|l| l.expression.clone());
+    let dependency_licenses = license::resolve_dependencies(dependencies.keys()).await;
+    // prefer the README that was actually packaged over the one in the repository
     let scoring_data = ScoringData {
-        readme_exists,
-        ..github::graphql::query(url).await?
+        readme: readme.or(scoring_data.readme),
+        documentation: Some(Documentation::from_path(&path, homepage.as_deref())),
+        test_suite: Some(TestSuite::from_path(
+            &path,
+            scripts.get("test").map(String::as_str),
+        )),
+        licenses,
+        dependency_licenses,
+        ..scoring_data
     };
 
-    let good_pinning_practice = version::score_versionreq_pinned(dependencies);
+    let good_pinning_practice = version::score_pinning(
+        DependencySections {
+            dependencies: dependencies.clone(),
+            dev_dependencies,
+            peer_dependencies,
+            optional_dependencies,
+        },
+        version::PinningWeights::get(),
+        version::find_lockfile(&path).is_some_and(|lockfile| lockfile.pins_full_tree()),
+    );
 
     // TODO!
     let pull_request = 1.;
 
-    let scores = (scoring_data, good_pinning_practice, pull_request).into();
-    Ok((name, version, scores))
+    Ok(PathRating {
+        name,
+        version,
+        license,
+        dependencies,
+        rating: (scoring_data, good_pinning_practice, pull_request).into(),
+    })
 }
 
 fn find_file<P: AsRef<Path>, F: Fn(&OsStr) -> bool>(haystack: P, needle: F) -> Option<DirEntry> {
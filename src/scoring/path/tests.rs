@@ -78,6 +78,12 @@ fn parse_package_json() {
         version,
         repository,
         dependencies,
+        dev_dependencies,
+        peer_dependencies,
+        optional_dependencies,
+        homepage,
+        scripts,
+        license,
     } = package_json
     else {
         panic!("Expected package_json to be the Deep variant\n{:?}", package_json);
@@ -90,6 +96,18 @@ fn parse_package_json() {
 
     assert_eq!(name, "elm-test");
     assert_eq!(version, "0.19.1-revision12".parse().unwrap());
+    assert_eq!(
+        homepage.as_deref(),
+        Some("https://github.com/rtfeldman/node-test-runner#readme")
+    );
+    assert_eq!(
+        scripts.as_ref().and_then(|s| s.get("test")).map(String::as_str),
+        Some("npm run check && npm run test-only")
+    );
+    assert_eq!(
+        license,
+        Some(PackageJsonLicense::Spdx("BSD-3-Clause".to_string()))
+    );
     assert_eq!(
         repository,
         Repository {
@@ -114,6 +132,12 @@ fn parse_package_json() {
     );
 
     assert_eq!(version::score_versionreq_pinned(dependencies), 0.);
+    assert_eq!(
+        dev_dependencies.map(version::score_versionreq_pinned),
+        Some(1.)
+    );
+    assert_eq!(peer_dependencies, None);
+    assert_eq!(optional_dependencies, None);
 }
 
 #[test]
@@ -131,6 +155,12 @@ fn parse_package_json_simple() {
         version,
         repository,
         dependencies,
+        dev_dependencies,
+        peer_dependencies,
+        optional_dependencies,
+        homepage,
+        scripts,
+        license,
     } = package_json
     else {
         panic!("Expected package_json to be the Flat variant\n{:?}", package_json);
@@ -140,4 +170,10 @@ fn parse_package_json_simple() {
     assert_eq!(version, "1.2.3".parse().unwrap());
     assert_eq!(repository, "https://github.com/fake/repo");
     assert_eq!(dependencies, None);
+    assert_eq!(dev_dependencies, None);
+    assert_eq!(peer_dependencies, None);
+    assert_eq!(optional_dependencies, None);
+    assert_eq!(homepage, None);
+    assert_eq!(scripts, None);
+    assert_eq!(license, None);
 }
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests;
+
+/// Number of words after which a README is considered long enough to be useful
+const FULL_LENGTH_WORDS: usize = 400;
+
+/// The file names (without extension) a README is expected to have
+static README_STEMS: [&str; 2] = ["readme", "read_me"];
+/// The extensions a README is expected to have, if it has one
+static README_EXTENSIONS: [&str; 7] = ["md", "markdown", "rst", "txt", "adoc", "asciidoc", "org"];
+
+/// Headings that indicate a section on how to install the package
+static INSTALL_HEADINGS: [&str; 4] = ["install", "getting started", "setup", "quick start"];
+/// Headings that indicate a section on how to use the package
+static USAGE_HEADINGS: [&str; 5] = ["usage", "example", "how to use", "tutorial", "api"];
+
+/// Check if a file name looks like a README, such as `README`, `readme.markdown` or `README.rst`
+pub(super) fn is_readme(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, Some(extension)),
+        None => (name.as_str(), None),
+    };
+
+    README_STEMS.contains(&stem)
+        && extension.is_none_or(|extension| README_EXTENSIONS.contains(&extension))
+}
+
+/// The parts of a README that indicate how easy it is for a new user to get started
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Readme {
+    words: usize,
+    install_section: bool,
+    usage_section: bool,
+}
+
+impl Readme {
+    pub(super) fn new(text: &str) -> Self {
+        // pad every word with a space so keywords only match at the start of a word
+        let headings: Vec<_> = headings(text)
+            .map(|h| {
+                h.to_lowercase()
+                    .split(|c: char| !c.is_alphanumeric())
+                    .fold(String::new(), |acc, word| acc + " " + word)
+            })
+            .collect();
+        let has_heading = |keywords: &[&str]| {
+            headings.iter().any(|h| {
+                keywords
+                    .iter()
+                    .any(|keyword| h.contains(&format!(" {}", keyword)))
+            })
+        };
+
+        let lowercase = text.to_lowercase();
+        let install_command = ["npm install", "npm i ", "yarn add", "pnpm add"]
+            .iter()
+            .any(|command| lowercase.contains(command));
+
+        Readme {
+            words: text.split_whitespace().count(),
+            install_section: install_command || has_heading(&INSTALL_HEADINGS),
+            usage_section: has_heading(&USAGE_HEADINGS),
+        }
+    }
+
+    /// Score in [0, 1], weighing length and the presence of install and usage instructions
+    pub(super) fn readability(&self) -> f64 {
+        let length = (self.words as f64 / FULL_LENGTH_WORDS as f64).min(1.);
+        let install = if self.install_section { 1. } else { 0. };
+        let usage = if self.usage_section { 1. } else { 0. };
+        (4. * length + 3. * install + 3. * usage) / 10.
+    }
+}
+
+/// Find the headings of a Markdown, reStructuredText or AsciiDoc document.
+///
+/// Recognizes `#`/`=` prefixed lines, and lines underlined with `=`, `-`, `~` or `^`.
+fn headings(text: &str) -> impl Iterator<Item = &str> {
+    let lines: Vec<_> = text.lines().map(str::trim).collect();
+    let underline =
+        |line: &str| !line.is_empty() && line.chars().all(|c| matches!(c, '=' | '-' | '~' | '^'));
+
+    (0..lines.len()).filter_map(move |i| {
+        let line = lines[i];
+        if line.is_empty() || underline(line) {
+            None
+        } else if line.starts_with('#') || line.starts_with('=') {
+            Some(line.trim_start_matches(['#', '=']).trim())
+        } else if lines.get(i + 1).is_some_and(|next| underline(next)) {
+            Some(line)
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+#[test]
+fn readme_names() {
+    assert!(is_readme("README"));
+    assert!(is_readme("README.md"));
+    assert!(is_readme("readme.markdown"));
+    assert!(is_readme("Readme.rst"));
+    assert!(is_readme("README.txt"));
+
+    assert!(!is_readme("README.js"));
+    assert!(!is_readme("readme-template.md"));
+    assert!(!is_readme("index.md"));
+}
+
+#[test]
+fn markdown_sections() {
+    let text = r#"# my-package
+
+Does things.
+
+## Installation
+
+```
+npm install my-package
+```
+
+## Usage
+
+Call it.
+"#;
+    assert_eq!(
+        Readme::new(text),
+        Readme {
+            words: 15,
+            install_section: true,
+            usage_section: true,
+        }
+    );
+}
+
+#[test]
+fn rst_sections() {
+    let text = r#"my-package
+==========
+
+Getting Started
+---------------
+
+Run the thing.
+
+Examples
+~~~~~~~~
+"#;
+    let readme = Readme::new(text);
+    assert!(readme.install_section);
+    assert!(readme.usage_section);
+}
+
+#[test]
+fn keywords_match_word_start() {
+    // "Rapid" should not count as an "API" section
+    let readme = Readme::new("# Rapid development\n\nSome text\n");
+    assert!(!readme.usage_section);
+    assert!(!readme.install_section);
+
+    let readme = Readme::new("# API reference\n");
+    assert!(readme.usage_section);
+}
+
+#[test]
+fn install_command_without_heading() {
+    let readme = Readme::new("Just run `yarn add my-package` and you're done");
+    assert!(readme.install_section);
+    assert!(!readme.usage_section);
+}
+
+#[test]
+fn readability_score() {
+    let empty = Readme {
+        words: 0,
+        install_section: false,
+        usage_section: false,
+    };
+    assert_eq!(empty.readability(), 0.);
+
+    let complete = Readme {
+        words: FULL_LENGTH_WORDS * 2,
+        install_section: true,
+        usage_section: true,
+    };
+    assert_eq!(complete.readability(), 1.);
+
+    let short = Readme {
+        words: FULL_LENGTH_WORDS / 2,
+        install_section: false,
+        usage_section: true,
+    };
+    assert_eq!(short.readability(), 0.5);
+}
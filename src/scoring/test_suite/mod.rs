@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests;
+
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Directory names that hold tests
+static TEST_DIRS: [&str; 5] = ["test", "tests", "__tests__", "spec", "specs"];
+/// Infixes of file names that hold tests, like `index.test.js`
+static TEST_FILE_INFIXES: [&str; 2] = [".test.", ".spec."];
+
+/// What `npm init` puts in the `"test"` script when the author didn't write any tests
+const NPM_DEFAULT_TEST_SCRIPT: &str = "no test specified";
+
+/// Whether a package ships with tests and a way to run them
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct TestSuite {
+    test_script: bool,
+    test_files: bool,
+}
+
+impl TestSuite {
+    /// Look through the files under `path`.
+    /// `test_script` is the `"test"` entry of the `"scripts"` in the package's `package.json`.
+    pub(super) fn from_path<P: AsRef<Path>>(path: P, test_script: Option<&str>) -> Self {
+        let test_files = WalkDir::new(path)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != "node_modules")
+            .filter_map(|e| e.ok())
+            .any(|entry| {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                if entry.file_type().is_dir() {
+                    TEST_DIRS.contains(&name.as_str())
+                } else {
+                    TEST_FILE_INFIXES.iter().any(|infix| name.contains(infix))
+                }
+            });
+
+        TestSuite {
+            test_script: test_script.is_some_and(is_real_test_script),
+            test_files,
+        }
+    }
+
+    /// Score in [0, 1], half for having tests and half for having a script to run them
+    pub(super) fn score(&self) -> f64 {
+        let flag = |b: bool| if b { 0.5 } else { 0. };
+        flag(self.test_script) + flag(self.test_files)
+    }
+}
+
+fn is_real_test_script(script: &str) -> bool {
+    !script.trim().is_empty() && !script.contains(NPM_DEFAULT_TEST_SCRIPT)
+}
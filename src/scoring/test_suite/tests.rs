@@ -0,0 +1,57 @@
+use super::*;
+
+use crate::queries::types::PackageId;
+use std::fs;
+
+#[test]
+fn default_test_script() {
+    assert!(!is_real_test_script(
+        r#"echo "Error: no test specified" && exit 1"#
+    ));
+    assert!(!is_real_test_script(""));
+    assert!(is_real_test_script("mocha tests && npm run elm-test"));
+}
+
+#[test]
+fn score_halves() {
+    assert_eq!(TestSuite::default().score(), 0.);
+    let script_only = TestSuite {
+        test_script: true,
+        test_files: false,
+    };
+    assert_eq!(script_only.score(), 0.5);
+    let both = TestSuite {
+        test_script: true,
+        test_files: true,
+    };
+    assert_eq!(both.score(), 1.);
+}
+
+#[test]
+fn from_path_finds_tests() {
+    let path = std::env::temp_dir().join(PackageId::new().as_ref());
+    fs::create_dir_all(path.join("package/lib")).unwrap();
+    fs::write(path.join("package/lib/index.test.js"), "").unwrap();
+
+    let test_suite = TestSuite::from_path(&path, Some("jest"));
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(
+        test_suite,
+        TestSuite {
+            test_script: true,
+            test_files: true,
+        }
+    );
+}
+
+#[test]
+fn from_path_ignores_dependencies() {
+    let path = std::env::temp_dir().join(PackageId::new().as_ref());
+    fs::create_dir_all(path.join("package/node_modules/dep/test")).unwrap();
+
+    let test_suite = TestSuite::from_path(&path, None);
+    fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(test_suite, TestSuite::default());
+}
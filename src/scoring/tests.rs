@@ -0,0 +1,399 @@
+use super::*;
+
+use base64::Engine;
+
+#[test]
+fn correctness_no_issues_is_not_nan() {
+    let score = correctness(0, 0, &CheckResults::default(), None);
+    assert_eq!(score, 0.);
+
+    let score = correctness(0, 0, &CheckResults::default(), Some(&TestSuite::default()));
+    assert_eq!(score, 0.);
+}
+
+#[test]
+fn correctness_missing_parts_reweighted() {
+    // only issues known
+    assert_eq!(correctness(3, 4, &CheckResults::default(), None), 0.75);
+
+    // only checks known
+    let checks = CheckResults {
+        passed: 1,
+        failed: 1,
+    };
+    assert_eq!(correctness(0, 0, &checks, None), 0.5);
+}
+
+#[test]
+fn correctness_all_parts() {
+    let checks = CheckResults {
+        passed: 2,
+        failed: 0,
+    };
+    assert_eq!(correctness(4, 4, &checks, Some(&TestSuite::default())), 0.7);
+}
+
+#[test]
+fn check_pass_rate() {
+    assert_eq!(CheckResults::default().pass_rate(), None);
+    let checks = CheckResults {
+        passed: 3,
+        failed: 1,
+    };
+    assert_eq!(checks.pass_rate(), Some(0.75));
+}
+
+#[test]
+fn bus_factor_authors_for_half() {
+    assert_eq!(bus_factor(vec![]), 0.);
+    assert_eq!(bus_factor(vec![10]), 0.);
+    // one author made most of the commits
+    assert_eq!(bus_factor(vec![1, 8, 1]), 0.);
+    // two authors needed to reach half
+    assert_eq!(bus_factor(vec![3, 3, 2, 2]), 0.5);
+    // four equal authors, two of them make up half
+    assert_eq!(bus_factor(vec![5, 5, 5, 5]), 0.5);
+    assert_eq!(bus_factor(vec![1; 10]), 0.8);
+}
+
+/// `package.json` of the package the stand-in registry and GitHub serve
+const LEFT_PAD_PACKAGE_JSON: &str = r#"{
+  "name": "left-pad",
+  "version": "1.3.0",
+  "license": "WTFPL",
+  "repository": {
+    "type": "git",
+    "url": "git+https://github.com/stevemao/left-pad.git"
+  }
+}"#;
+
+/// Recorded from `registry.npmjs.org`, trimmed, with the tarball pointing at the stand-in and its
+/// digest replaced by that of the stand-in's tarball
+const LEFT_PAD_METADATA: &str = r#"{
+  "name": "left-pad",
+  "dist-tags": { "latest": "1.3.0" },
+  "versions": {
+    "1.3.0": {
+      "dist": {
+        "tarball": "{address}/tarballs/left-pad-1.3.0.tgz",
+        "integrity": "{integrity}"
+      }
+    }
+  }
+}"#;
+
+/// What the stand-in says every reference of `stevemao/left-pad` points to
+const LEFT_PAD_COMMIT: &str = "431ba4d2e9a7b58b89f1fa0d6cd9a8a5bf2a4f53";
+
+/// Recorded from `api.github.com/graphql`, trimmed
+const LEFT_PAD_GRAPHQL: &str = r#"{
+  "data": {
+    "rateLimit": { "cost": 1, "remaining": 4999, "resetAt": "2999-01-01T00:00:00Z" },
+    "repository": {
+      "issuesOpen": { "totalCount": 23 },
+      "issuesClosed": { "totalCount": 17 },
+      "recentIssues": { "nodes": [] },
+      "recentPullRequests": { "nodes": [] },
+      "head": null,
+      "licenseInfo": { "spdxId": "WTFPL", "pseudoLicense": false }
+    }
+  }
+}"#;
+
+const LEFT_PAD_HISTORY: &str = r#"{
+  "data": {
+    "rateLimit": { "cost": 1, "remaining": 4998, "resetAt": "2999-01-01T00:00:00Z" },
+    "repository": { "head": null }
+  }
+}"#;
+
+/// Start a stand-in for GitHub, npm and GitLab serving recorded responses, and point scoring at
+/// it.
+/// It runs on its own thread, since each test has its own runtime but the upstream is only
+/// configured once.
+fn stand_in() -> &'static str {
+    use axum::{
+        extract::{Json, Path},
+        http::{header::AUTHORIZATION, HeaderMap},
+        routing::{get, post},
+        Router,
+    };
+    use once_cell::sync::OnceCell;
+
+    static ADDRESS: OnceCell<String> = OnceCell::new();
+    ADDRESS.get_or_init(|| {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+
+        let tarball = left_pad_tarball();
+        let metadata = LEFT_PAD_METADATA
+            .replace("{address}", &address)
+            .replace("{integrity}", &left_pad_integrity(&tarball));
+        let app = Router::new()
+            .route(
+                "/graphql",
+                post(|Json(body): Json<serde_json::Value>| async move {
+                    match body["operationName"].as_str() {
+                        Some("HistoryQuery") => LEFT_PAD_HISTORY,
+                        _ => LEFT_PAD_GRAPHQL,
+                    }
+                }),
+            )
+            .route(
+                "/npm/:name",
+                get(|Path(name): Path<String>| async move {
+                    match name.as_str() {
+                        "left-pad" => Ok(metadata),
+                        _ => Err(axum::http::StatusCode::NOT_FOUND),
+                    }
+                }),
+            )
+            .route("/tarballs/left-pad-1.3.0.tgz", get(|| async { tarball }))
+            .route(
+                "/repos/stevemao/left-pad/commits/:reference",
+                get(
+                    |Path(reference): Path<String>, headers: HeaderMap| async move {
+                        // sent with the token, like every request to GitHub
+                        if !headers.contains_key(AUTHORIZATION) {
+                            return Err(axum::http::StatusCode::UNAUTHORIZED);
+                        }
+                        match reference.as_str() {
+                            "HEAD" | "v1.3.0" | LEFT_PAD_COMMIT => Ok(LEFT_PAD_COMMIT),
+                            _ => Err(axum::http::StatusCode::NOT_FOUND),
+                        }
+                    },
+                ),
+            )
+            .route(
+                &format!("/repos/stevemao/left-pad/zipball/{}", LEFT_PAD_COMMIT),
+                get(|| async { left_pad_zipball() }),
+            );
+
+        std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .await
+                })
+                .unwrap()
+        });
+
+        std::env::set_var("GITHUB_API_URL", &address);
+        std::env::set_var("GITHUB_GRAPHQL_URL", format!("{}/graphql", address));
+        std::env::set_var("NPM_REGISTRY_URL", format!("{}/npm", address));
+        // no projects at all
+        std::env::set_var("GITLAB_API_URL", format!("{}/gitlab", address));
+        if std::env::var("GITHUB_TOKEN").is_err() {
+            std::env::set_var("GITHUB_TOKEN", "stand-in");
+        }
+        address
+    })
+}
+
+fn left_pad_tarball() -> Vec<u8> {
+    let mut tar = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(LEFT_PAD_PACKAGE_JSON.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tar.append_data(
+        &mut header,
+        "package/package.json",
+        LEFT_PAD_PACKAGE_JSON.as_bytes(),
+    )
+    .unwrap();
+
+    let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+    io::Write::write_all(&mut encoder, &tar.into_inner().unwrap()).unwrap();
+    encoder.finish().into_result().unwrap()
+}
+
+fn left_pad_integrity(tarball: &[u8]) -> String {
+    use sha2::Digest;
+    let digest = sha2::Sha512::digest(tarball);
+    format!("sha512-{}", general_purpose::STANDARD.encode(digest))
+}
+
+fn left_pad_zipball() -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    zip.start_file(
+        "stevemao-left-pad-5b1d8c3/package.json",
+        zip::write::FileOptions::default(),
+    )
+    .unwrap();
+    io::Write::write_all(&mut zip, LEFT_PAD_PACKAGE_JSON.as_bytes()).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn npm_against_recorded_responses() {
+    stand_in();
+    let rated = from_url("https://www.npmjs.com/package/left-pad", None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(rated.name, "left-pad");
+    assert_eq!(rated.version, Version::new(1, 3, 0));
+    assert_eq!(rated.license.as_deref(), Some("WTFPL"));
+    assert!(rated
+        .integrity
+        .is_some_and(|integrity| integrity.starts_with("sha512-")));
+    assert!((0. ..=1.).contains(&rated.rating.net_score));
+}
+
+#[tokio::test]
+async fn github_against_recorded_responses() {
+    stand_in();
+    let rated = from_url("https://github.com/stevemao/left-pad", None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(rated.name, "left-pad");
+    assert_eq!(rated.version, Version::new(1, 3, 0));
+    assert!(rated.content.starts_with(b"PK"));
+    assert_eq!(rated.commit.as_deref(), Some(LEFT_PAD_COMMIT));
+}
+
+#[tokio::test]
+async fn github_reference() {
+    stand_in();
+    let rated = from_url(
+        "https://github.com/stevemao/left-pad/tree/v1.3.0",
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rated.commit.as_deref(), Some(LEFT_PAD_COMMIT));
+
+    // the path after the tag is dropped, down to the longest part that resolves
+    let rated = from_url(
+        "https://github.com/stevemao/left-pad/tree/v1.3.0/lib/index.js",
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rated.commit.as_deref(), Some(LEFT_PAD_COMMIT));
+
+    let result = from_url(
+        "https://github.com/stevemao/left-pad/tree/v9/lib",
+        None,
+        None,
+    )
+    .await;
+    assert!(
+        matches!(&result, Err(GithubRefNotFound { reference, .. }) if reference == "v9/lib"),
+        "{:?}",
+        result.err()
+    );
+
+    // never sent on to move up the request's path
+    let result = from_url(
+        "https://github.com/stevemao/left-pad",
+        None,
+        Some("../../x"),
+    )
+    .await;
+    assert!(matches!(result, Err(GithubRefNotFound { .. })));
+
+    let result = from_url("https://github.com/stevemao/left-pad", None, Some("v9")).await;
+    assert!(
+        matches!(&result, Err(GithubRefNotFound { reference, .. }) if reference == "v9"),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[tokio::test]
+async fn npm_unknown_package() {
+    stand_in();
+    let result = npm_metadata("not-a-package").await;
+    assert!(
+        matches!(&result, Err(ReqwestError(e)) if e.status() == Some(reqwest::StatusCode::NOT_FOUND)),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[tokio::test]
+async fn fields_not_applicable() {
+    let not_applicable = |result: RatingResult<RatedPackage>| match result {
+        Err(FieldNotApplicable { field, .. }) => Some(field),
+        _ => None,
+    };
+    let github = "https://github.com/stevemao/left-pad";
+    let gitlab = "https://gitlab.com/abc/def";
+    let npm = "https://www.npmjs.com/package/left-pad";
+
+    assert_eq!(
+        not_applicable(from_url(github, Some("1.3.0"), None).await),
+        Some("Version")
+    );
+    assert_eq!(
+        not_applicable(from_url(gitlab, Some("1.3.0"), None).await),
+        Some("Version")
+    );
+    assert_eq!(
+        not_applicable(from_url(gitlab, None, Some("main")).await),
+        Some("Ref")
+    );
+    assert_eq!(
+        not_applicable(from_url(npm, None, Some("main")).await),
+        Some("Ref")
+    );
+}
+
+#[tokio::test]
+async fn gitlab_missing_project() {
+    stand_in();
+    let result = from_url("https://gitlab.com/abc/not-a-project", None, None).await;
+    assert!(
+        matches!(&result, Err(RepositoryNotFound { repository }) if repository == "gitlab.com/abc/not-a-project"),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[tokio::test]
+async fn npm_requested_version_missing() {
+    stand_in();
+    let result = from_url(
+        "https://www.npmjs.com/package/left-pad/v/1.3.0",
+        Some("next"),
+        None,
+    )
+    .await;
+    assert!(
+        matches!(&result, Err(NpmVersionNotFound { name, requested }) if name == "left-pad" && requested == "next"),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[tokio::test]
+async fn content_tarball() {
+    stand_in();
+    let tarball = left_pad_tarball();
+    let content = general_purpose::STANDARD.encode(&tarball);
+    let rated = from_content(content.into_bytes()).await.unwrap();
+    assert_eq!(rated.name, "left-pad");
+    assert_eq!(rated.version, Version::new(1, 3, 0));
+    // sized as uploaded, not as the base64 it's kept in
+    assert_eq!(rated.content_size, tarball.len() as u64);
+}
+
+#[tokio::test]
+async fn npm_keeps_tarball() {
+    stand_in();
+    let rated = from_url("https://www.npmjs.com/package/left-pad", None, None)
+        .await
+        .unwrap();
+    assert!(rated.tarball.is_some_and(|tarball| is_gzip(&tarball)));
+    assert!(rated.content.starts_with(b"PK"));
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests;
+
+use super::url::encode_npm_name;
+
+use once_cell::sync::OnceCell;
+
+const GITHUB_API: &str = "https://api.github.com";
+const NPM_REGISTRY: &str = "https://registry.npmjs.org";
+
+/// Where the GitHub API and npm registry are, so GitHub Enterprise, a private registry, or a
+/// local stand-in can be used instead
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Upstream {
+    github_api: String,
+    github_graphql: String,
+    npm_registry: String,
+    /// used for every GitLab host instead of its own API, if set
+    gitlab_api: Option<String>,
+}
+
+/// Set with `GITHUB_API_URL`, `GITHUB_GRAPHQL_URL`, `NPM_REGISTRY_URL` and `GITLAB_API_URL`,
+/// the public GitHub and npm, and the API of each GitLab host, by default
+pub(super) fn get() -> &'static Upstream {
+    static UPSTREAM: OnceCell<Upstream> = OnceCell::new();
+    UPSTREAM.get_or_init(|| {
+        let var = |name| std::env::var(name).ok().filter(|url| !url.is_empty());
+        Upstream {
+            gitlab_api: var("GITLAB_API_URL").map(|url| url.trim_end_matches('/').to_owned()),
+            ..Upstream::new(
+                var("GITHUB_API_URL"),
+                var("GITHUB_GRAPHQL_URL"),
+                var("NPM_REGISTRY_URL"),
+            )
+        }
+    })
+}
+
+impl Upstream {
+    fn new(
+        github_api: Option<String>,
+        github_graphql: Option<String>,
+        npm_registry: Option<String>,
+    ) -> Self {
+        let trim = |url: String| url.trim_end_matches('/').to_owned();
+        let github_api = github_api.map_or_else(|| GITHUB_API.to_owned(), trim);
+        let github_graphql = github_graphql.map_or_else(|| graphql_for(&github_api), trim);
+        Upstream {
+            github_api,
+            github_graphql,
+            npm_registry: npm_registry.map_or_else(|| NPM_REGISTRY.to_owned(), trim),
+            gitlab_api: None,
+        }
+    }
+
+    pub(super) fn github_graphql(&self) -> &str {
+        &self.github_graphql
+    }
+
+    pub(super) fn github_zipball(&self, owner: &str, name: &str, reference: &str) -> String {
+        format!(
+            "{}/repos/{}/{}/zipball/{}",
+            self.github_api,
+            owner,
+            name,
+            encode_reference(reference)
+        )
+    }
+
+    /// The commit a tag, branch or SHA refers to
+    pub(super) fn github_commit(&self, owner: &str, name: &str, reference: &str) -> String {
+        format!(
+            "{}/repos/{}/{}/commits/{}",
+            self.github_api,
+            owner,
+            name,
+            encode_reference(reference)
+        )
+    }
+
+    pub(super) fn npm_package(&self, name: &str) -> String {
+        format!("{}/{}", self.npm_registry, encode_npm_name(name))
+    }
+
+    /// REST API of the GitLab instance at `host`
+    pub(super) fn gitlab_api(&self, host: &str) -> String {
+        match &self.gitlab_api {
+            Some(api) => api.clone(),
+            None => format!("https://{}/api/v4", host),
+        }
+    }
+
+    /// Plain HTTP is only allowed when an upstream was configured to use it
+    pub(super) fn https_only(&self) -> bool {
+        [&self.github_api, &self.github_graphql, &self.npm_registry]
+            .into_iter()
+            .chain(&self.gitlab_api)
+            .all(|url| url.starts_with("https://"))
+    }
+}
+
+/// GitHub Enterprise serves REST under `/api/v3` and GraphQL at `/api/graphql`
+fn graphql_for(github_api: &str) -> String {
+    match github_api.strip_suffix("/v3") {
+        Some(api) => format!("{}/graphql", api),
+        None => format!("{}/graphql", github_api),
+    }
+}
+
+/// A tag, branch or commit as part of a URL path. Each segment of a name like `feature/thing` is
+/// percent-encoded, so a `?` or `#` in it can't change the rest of the request.
+fn encode_reference(reference: &str) -> String {
+    reference
+        .split('/')
+        .map(|segment| ::url::form_urlencoded::byte_serialize(segment.as_bytes()).collect())
+        .collect::<Vec<String>>()
+        .join("/")
+}
@@ -0,0 +1,72 @@
+use super::*;
+
+#[test]
+fn defaults() {
+    let upstream = Upstream::new(None, None, None);
+    assert_eq!(upstream.github_graphql(), "https://api.github.com/graphql");
+    assert_eq!(
+        upstream.github_zipball("lodash", "lodash", "4.17.21"),
+        "https://api.github.com/repos/lodash/lodash/zipball/4.17.21"
+    );
+    assert_eq!(
+        upstream.github_commit("lodash", "lodash", "HEAD"),
+        "https://api.github.com/repos/lodash/lodash/commits/HEAD"
+    );
+    assert_eq!(
+        upstream.npm_package("chalk"),
+        "https://registry.npmjs.org/chalk"
+    );
+    assert_eq!(
+        upstream.npm_package("@types/node"),
+        "https://registry.npmjs.org/@types%2fnode"
+    );
+    assert_eq!(
+        upstream.gitlab_api("gitlab.example.com"),
+        "https://gitlab.example.com/api/v4"
+    );
+    assert!(upstream.https_only());
+}
+
+#[test]
+fn github_enterprise() {
+    let upstream = Upstream::new(
+        Some("https://github.example.com/api/v3/".to_owned()),
+        None,
+        None,
+    );
+    assert_eq!(
+        upstream.github_graphql(),
+        "https://github.example.com/api/graphql"
+    );
+    assert_eq!(
+        upstream.github_zipball("team", "tool", "main"),
+        "https://github.example.com/api/v3/repos/team/tool/zipball/main"
+    );
+}
+
+#[test]
+fn local_stand_in() {
+    let upstream = Upstream::new(
+        Some("http://127.0.0.1:8080".to_owned()),
+        Some("http://127.0.0.1:8080/graphql".to_owned()),
+        Some("http://127.0.0.1:8080/npm/".to_owned()),
+    );
+    assert_eq!(
+        upstream.npm_package("chalk"),
+        "http://127.0.0.1:8080/npm/chalk"
+    );
+    assert!(!upstream.https_only());
+}
+
+#[test]
+fn references_encoded() {
+    let upstream = Upstream::new(None, None, None);
+    assert_eq!(
+        upstream.github_commit("team", "tool", "feature/thing"),
+        "https://api.github.com/repos/team/tool/commits/feature/thing"
+    );
+    assert_eq!(
+        upstream.github_commit("team", "tool", "v1?per_page=1#x"),
+        "https://api.github.com/repos/team/tool/commits/v1%3Fper_page%3D1%23x"
+    );
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests;
 
-use super::{RatingError::*, RatingResult};
+use super::{git::GitRepository, host::RepositoryHost, upstream, RatingError::*, RatingResult};
 
 use git_url_parse::GitUrl;
 use once_cell::sync::OnceCell;
@@ -17,22 +17,84 @@ pub fn get_client() -> Client {
         .get_or_init(|| {
             Client::builder()
                 .user_agent(USER_AGENT)
-                .https_only(true)
+                .https_only(upstream::get().https_only())
                 .build()
                 .unwrap()
         })
         .clone()
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub(super) struct GithubUrl {
     pub(super) name: String,
     pub(super) owner: String,
+    /// tag, branch or commit to download, instead of the default branch
+    pub(super) reference: Option<String>,
+    /// whether the reference came from a link like `tree/main/src/lib`, so it may go on into a
+    /// path inside the repository
+    pub(super) reference_with_path: bool,
+}
+
+/// A project on gitlab.com or a self-hosted GitLab, where projects can be nested in subgroups
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct GitlabUrl {
+    pub(super) host: String,
+    /// full path of the project, like `group/subgroup/project`
+    pub(super) path: String,
+}
+
+/// Where the source of a package is hosted
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum RepositoryUrl {
+    Github(GithubUrl),
+    Gitlab(GitlabUrl),
+    /// anywhere else, reached with git itself
+    Git(GitRepository),
+}
+
+const GITLAB_COM: &str = "gitlab.com";
+
+/// Whether `host` runs GitLab: gitlab.com, or one of the comma separated hosts in `GITLAB_HOSTS`
+pub(super) fn is_gitlab_host(host: &str) -> bool {
+    static HOSTS: OnceCell<Vec<String>> = OnceCell::new();
+    HOSTS
+        .get_or_init(|| hosts_from_env("GITLAB_HOSTS", GITLAB_COM))
+        .iter()
+        .any(|gitlab| host.eq_ignore_ascii_case(gitlab))
+}
+
+/// The comma separated hosts in the environment `variable`, lowercased, along with `default`
+pub(super) fn hosts_from_env(variable: &str, default: &str) -> Vec<String> {
+    std::env::var(variable)
+        .unwrap_or_default()
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .chain([default.to_owned()])
+        .collect()
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub(super) struct NpmUrl {
+    /// may be scoped, like `@types/node`
     pub(super) name: String,
+    /// version or dist-tag, from links like `npmjs.com/package/abc/v/1.2.3`
+    pub(super) version: Option<String>,
+}
+
+/// How a package name goes in a registry URL: the slash of a scoped name is escaped, so
+/// `@types/node` is requested as `@types%2fnode`
+pub(super) fn encode_npm_name(name: &str) -> String {
+    let encode = |part: &str| -> String {
+        ::url::form_urlencoded::byte_serialize(part.as_bytes()).collect()
+    };
+    let scoped = name
+        .strip_prefix('@')
+        .and_then(|scoped| scoped.split_once('/'));
+    match scoped {
+        Some((scope, name)) => format!("@{}%2f{}", encode(scope), encode(name)),
+        None => encode(name),
+    }
 }
 
 impl<T> TryFrom<Result<GitUrl, T>> for GithubUrl {
@@ -54,43 +116,77 @@ impl TryFrom<GitUrl> for GithubUrl {
                 name,
                 owner: Some(owner),
                 ..
-            } if host.contains("github.com") => Ok(GithubUrl { name, owner }),
+            } if host.contains("github.com") => Ok(GithubUrl {
+                name,
+                owner,
+                reference: None,
+                reference_with_path: false,
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryFrom<GitUrl> for GitlabUrl {
+    type Error = ();
+    fn try_from(value: GitUrl) -> Result<Self, Self::Error> {
+        match value.host {
+            Some(host) if is_gitlab_host(&host) => {
+                let path = value.path.trim_matches('/').trim_end_matches(".git");
+                if path.contains('/') {
+                    Ok(GitlabUrl {
+                        path: path.to_owned(),
+                        host,
+                    })
+                } else {
+                    Err(())
+                }
+            }
             _ => Err(()),
         }
     }
 }
 
 /// Transform something that could be put into the `"repository"` field of an npm `package.json`
-/// into a `GithubUrl`.
-pub(super) fn canonicalize_repo(url: &str) -> RatingResult<GithubUrl> {
+/// into a `RepositoryUrl`.
+pub(super) fn canonicalize_repo(url: &str) -> RatingResult<RepositoryUrl> {
     let err = || UrlParseError(url.to_string());
 
     let url = url.trim_start_matches("git+");
 
-    if let Ok(git_url) = GitUrl::parse(url) {
-        if let Ok(github_url) = git_url.try_into() {
-            return Ok(github_url);
-        }
+    if let Some(repository) = RepositoryUrl::parse(url) {
+        return Ok(repository);
     }
 
     // this is of the "shorthand" type, see
     // https://docs.npmjs.com/cli/v9/configuring-npm/package-json#repository
     // "abc/def" => github link
     // "github:abc/def" => github link
+    // "gitlab:abc/def" => gitlab link
+    // "bitbucket:abc/def" => bitbucket link, cloned with git
     // "blabla:abc/def" => not supported
 
-    let len = url.split(':').count();
-    let mut rest = url.split(':');
-    if len > 1 && rest.next().unwrap() != "github" {
-        return Err(err());
-    }
-
-    let mut split_slash = rest.next().ok_or_else(err)?.split('/');
-    match (split_slash.next(), split_slash.next()) {
-        (Some(owner), Some(name)) => Ok(GithubUrl {
-            name: name.to_string(),
-            owner: owner.to_string(),
-        }),
+    let (provider, shorthand) = url.split_once(':').unwrap_or(("github", url));
+    match provider {
+        "github" => {
+            let mut split_slash = shorthand.split('/');
+            match (split_slash.next(), split_slash.next()) {
+                (Some(owner), Some(name)) => Ok(RepositoryUrl::Github(GithubUrl {
+                    name: name.to_string(),
+                    owner: owner.to_string(),
+                    reference: None,
+                    reference_with_path: false,
+                })),
+                _ => Err(err()),
+            }
+        }
+        "gitlab" if shorthand.contains('/') => Ok(RepositoryUrl::Gitlab(GitlabUrl {
+            host: GITLAB_COM.to_owned(),
+            path: shorthand.trim_matches('/').to_owned(),
+        })),
+        "bitbucket" if shorthand.contains('/') => {
+            Ok(RepositoryUrl::Git(GitRepository::bitbucket(shorthand)))
+        }
         _ => Err(err()),
     }
 }
@@ -110,12 +206,23 @@ pub(super) fn canonicalize_repo(url: &str) -> RatingResult<GithubUrl> {
 #[derive(Debug, PartialEq, Eq)]
 pub(super) enum UrlKind {
     Github(GithubUrl),
+    Gitlab(GitlabUrl),
+    Git(GitRepository),
     Npm(NpmUrl),
 }
 
 impl TryFrom<&str> for UrlKind {
     type Error = ();
+    /// Links to sites other than GitHub, GitLab and npm are only accepted if they are clearly git
+    /// repositories
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_known_site(value)
+            .or_else(|()| GitRepository::parse(value).map(Self::Git).ok_or(()))
+    }
+}
+
+impl UrlKind {
+    fn from_known_site(value: &str) -> Result<Self, ()> {
         let url: ::url::Url = value
             .parse()
             .map_err(|e| log::error!("error parsing url: {}", e))?;
@@ -125,6 +232,22 @@ impl TryFrom<&str> for UrlKind {
             return Err(())
         };
 
+        // self-hosted GitLab can be on any domain
+        if is_gitlab_host(host) {
+            // links to pages of a project look like `group/project/-/tree/main`
+            let path = url.path().trim_matches('/');
+            let path = path.split("/-/").next().unwrap_or_default();
+            let path = path.trim_end_matches(".git");
+            return if path.contains('/') {
+                Ok(Self::Gitlab(GitlabUrl {
+                    host: host.to_owned(),
+                    path: path.to_owned(),
+                }))
+            } else {
+                Err(())
+            };
+        }
+
         // get parts of FQDN from right to left
         let mut domain_parts = host.rsplit('.');
 
@@ -138,9 +261,24 @@ impl TryFrom<&str> for UrlKind {
             Some(site) if site == "github" => {
                 let mut split = url.path().trim_matches('/').split('/');
                 if let (Some(owner), Some(name)) = (split.next(), split.next()) {
+                    // links to a tag, branch or commit, which may have slashes in its name.
+                    // Pages under a branch go on with a path, so where its name ends is only
+                    // known once it's looked up.
+                    let rest: Vec<&str> = split.collect();
+                    let (reference, reference_with_path) = match rest.as_slice() {
+                        ["tree" | "blob" | "commit", reference @ ..] if !reference.is_empty() => {
+                            (Some(reference.join("/")), true)
+                        }
+                        ["releases", "tag", reference @ ..] if !reference.is_empty() => {
+                            (Some(reference.join("/")), false)
+                        }
+                        _ => (None, false),
+                    };
                     Ok(Self::Github(GithubUrl {
                         name: name.trim_end_matches(".git").to_owned(),
                         owner: owner.to_owned(),
+                        reference,
+                        reference_with_path,
                     }))
                 } else {
                     Err(())
@@ -154,9 +292,20 @@ impl TryFrom<&str> for UrlKind {
                     if name == "package" {
                         name = split.next().ok_or(())?;
                     }
-                    Ok(Self::Npm(NpmUrl {
-                        name: name.to_owned(),
-                    }))
+                    // scoped packages take two segments, "npmjs.com/package/@scope/abc"
+                    let name = if name.starts_with('@') {
+                        let scoped = split.next().filter(|s| !s.is_empty()).ok_or(())?;
+                        format!("{}/{}", name, scoped)
+                    } else {
+                        name.to_owned()
+                    };
+                    let version = match (split.next(), split.next()) {
+                        (Some("v"), Some(version)) if !version.is_empty() => {
+                            Some(version.to_owned())
+                        }
+                        _ => None,
+                    };
+                    Ok(Self::Npm(NpmUrl { name, version }))
                 } else {
                     Err(())
                 }
@@ -169,13 +318,20 @@ impl TryFrom<&str> for UrlKind {
 #[derive(Deserialize, Debug, PartialEq, Eq)]
 pub(super) struct NpmAbbrMetadata {
     #[serde(rename = "dist-tags")]
-    pub(super) dist_tags: NpmDistTags,
+    pub(super) dist_tags: HashMap<String, Version>,
     pub(super) versions: HashMap<Version, NpmVersion>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub(super) struct NpmDistTags {
-    pub(super) latest: Version,
+impl NpmAbbrMetadata {
+    /// The version that `requested` names, either exactly or through a dist-tag, or the
+    /// `latest` one if nothing was requested
+    pub(super) fn resolve(&self, requested: Option<&str>) -> Option<&NpmVersion> {
+        let requested = requested.map_or("latest", str::trim);
+        match requested.trim_start_matches('v').parse::<Version>() {
+            Ok(version) => self.versions.get(&version),
+            Err(_) => self.versions.get(self.dist_tags.get(requested)?),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq)]
@@ -186,4 +342,10 @@ pub(super) struct NpmVersion {
 #[derive(Deserialize, Debug, PartialEq, Eq)]
 pub(super) struct NpmDist {
     pub(super) tarball: String,
+    /// Subresource Integrity string, like `sha512-<base64>`
+    #[serde(default)]
+    pub(super) integrity: Option<String>,
+    /// hex sha1 of the tarball, given for every version including ones from before `integrity`
+    #[serde(default)]
+    pub(super) shasum: Option<String>,
 }
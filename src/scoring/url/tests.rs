@@ -5,9 +5,11 @@ fn canon_repo_full_link() {
     let expected = GithubUrl {
         owner: "abc".to_string(),
         name: "def".to_string(),
+        reference: None,
+        reference_with_path: false,
     };
     let result = canonicalize_repo("https://github.com/abc/def").unwrap();
-    assert_eq!(result, expected);
+    assert_eq!(result, RepositoryUrl::Github(expected));
 }
 
 #[test]
@@ -15,9 +17,11 @@ fn canon_repo_short() {
     let expected = GithubUrl {
         owner: "abc".to_string(),
         name: "def".to_string(),
+        reference: None,
+        reference_with_path: false,
     };
     let result = canonicalize_repo("abc/def").unwrap();
-    assert_eq!(result, expected);
+    assert_eq!(result, RepositoryUrl::Github(expected));
 }
 
 #[test]
@@ -25,30 +29,69 @@ fn canon_repo_short_prefix() {
     let expected = GithubUrl {
         owner: "abc".to_string(),
         name: "def".to_string(),
+        reference: None,
+        reference_with_path: false,
     };
     let result = canonicalize_repo("github:abc/def").unwrap();
-    assert_eq!(result, expected);
+    assert_eq!(result, RepositoryUrl::Github(expected));
 }
 
 #[test]
 fn canon_repo_short_prefix_bad() {
-    let result = canonicalize_repo("gitlab:abc/def").unwrap_err();
-    assert!(matches!(result, UrlParseError(u) if u == "gitlab:abc/def"));
+    let result = canonicalize_repo("blabla:abc/def").unwrap_err();
+    assert!(matches!(result, UrlParseError(u) if u == "blabla:abc/def"));
+}
+
+#[test]
+fn canon_repo_bitbucket() {
+    let expected = RepositoryUrl::Git(GitRepository {
+        url: "https://bitbucket.org/abc/def.git".to_owned(),
+    });
+    assert_eq!(canonicalize_repo("bitbucket:abc/def").unwrap(), expected);
+    assert_eq!(
+        canonicalize_repo("git+https://bitbucket.org/abc/def.git").unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn canon_repo_gitlab() {
+    let expected = RepositoryUrl::Gitlab(GitlabUrl {
+        host: "gitlab.com".to_string(),
+        path: "abc/def".to_string(),
+    });
+    assert_eq!(canonicalize_repo("gitlab:abc/def").unwrap(), expected);
+    assert_eq!(
+        canonicalize_repo("git+https://gitlab.com/abc/def.git").unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn canon_repo_gitlab_subgroup() {
+    let expected = RepositoryUrl::Gitlab(GitlabUrl {
+        host: "gitlab.com".to_string(),
+        path: "abc/tools/def".to_string(),
+    });
+    assert_eq!(
+        canonicalize_repo("git@gitlab.com:abc/tools/def.git").unwrap(),
+        expected
+    );
 }
 
 #[test]
 fn npm_metadata_deserialize() {
     let data = r#"{"dist-tags":{"latest":"1.0.0"},"modified":"2015-05-16T22:27:54.741Z","name":"tiny-tarball","versions":{"1.0.0":{"_hasShrinkwrap":false,"directories":{},"dist":{"shasum":"bbf102d5ae73afe2c553295e0fb02230216f65b1","tarball":"https://registry.npmjs.org/tiny-tarball/-/tiny-tarball-1.0.0.tgz"},"name":"tiny-tarball","version":"1.0.0"}}}"#;
     let expected = NpmAbbrMetadata {
-        dist_tags: NpmDistTags {
-            latest: "1.0.0".parse().unwrap(),
-        },
+        dist_tags: HashMap::from([("latest".to_owned(), "1.0.0".parse().unwrap())]),
         versions: HashMap::from([(
             "1.0.0".parse().unwrap(),
             NpmVersion {
                 dist: NpmDist {
                     tarball: "https://registry.npmjs.org/tiny-tarball/-/tiny-tarball-1.0.0.tgz"
                         .to_string(),
+                    integrity: None,
+                    shasum: Some("bbf102d5ae73afe2c553295e0fb02230216f65b1".to_string()),
                 },
             },
         )]),
@@ -58,12 +101,54 @@ fn npm_metadata_deserialize() {
     assert_eq!(des, expected);
 }
 
+#[test]
+fn npm_metadata_resolve() {
+    let version = |tarball: &str| NpmVersion {
+        dist: NpmDist {
+            tarball: tarball.to_owned(),
+            integrity: None,
+            shasum: None,
+        },
+    };
+    let metadata = NpmAbbrMetadata {
+        dist_tags: HashMap::from([
+            ("latest".to_owned(), "1.0.0".parse().unwrap()),
+            ("next".to_owned(), "2.0.0-beta.1".parse().unwrap()),
+        ]),
+        versions: HashMap::from([
+            ("0.9.0".parse().unwrap(), version("old.tgz")),
+            ("1.0.0".parse().unwrap(), version("latest.tgz")),
+            ("2.0.0-beta.1".parse().unwrap(), version("next.tgz")),
+        ]),
+    };
+    let tarball = |requested| metadata.resolve(requested).map(|v| v.dist.tarball.as_str());
+
+    assert_eq!(tarball(None), Some("latest.tgz"));
+    assert_eq!(tarball(Some("next")), Some("next.tgz"));
+    assert_eq!(tarball(Some("0.9.0")), Some("old.tgz"));
+    assert_eq!(tarball(Some("v0.9.0")), Some("old.tgz"));
+    assert_eq!(tarball(Some("0.8.0")), None);
+    assert_eq!(tarball(Some("canary")), None);
+}
+
+#[test]
+fn url_kind_npm_version() {
+    let data = "https://www.npmjs.com/package/lodash/v/4.17.20";
+    let result: UrlKind = data.try_into().unwrap();
+    let expected = UrlKind::Npm(NpmUrl {
+        name: "lodash".to_owned(),
+        version: Some("4.17.20".to_owned()),
+    });
+    assert_eq!(result, expected);
+}
+
 #[test]
 fn url_kind_parse() {
     let data = "https://npmjs.com/abc";
     let result: UrlKind = data.try_into().unwrap();
     let expected = UrlKind::Npm(NpmUrl {
         name: "abc".to_owned(),
+        version: None,
     });
     assert_eq!(result, expected);
 
@@ -71,6 +156,7 @@ fn url_kind_parse() {
     let result: UrlKind = data.try_into().unwrap();
     let expected = UrlKind::Npm(NpmUrl {
         name: "abc".to_owned(),
+        version: None,
     });
     assert_eq!(result, expected);
 
@@ -78,6 +164,7 @@ fn url_kind_parse() {
     let result: UrlKind = data.try_into().unwrap();
     let expected = UrlKind::Npm(NpmUrl {
         name: "abc".to_owned(),
+        version: None,
     });
     assert_eq!(result, expected);
 
@@ -86,6 +173,8 @@ fn url_kind_parse() {
     let expected = UrlKind::Github(GithubUrl {
         name: "def".to_owned(),
         owner: "abc".to_owned(),
+        reference: None,
+        reference_with_path: false,
     });
     assert_eq!(result, expected);
 
@@ -97,6 +186,143 @@ fn url_kind_parse() {
     let expected = UrlKind::Github(GithubUrl {
         name: "node-test-runner".to_string(),
         owner: "rtfeldman".to_string(),
+        reference: None,
+        reference_with_path: false,
+    });
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn url_kind_gitlab() {
+    let data = "https://gitlab.com/abc/tools/def/-/tree/main";
+    let result: UrlKind = data.try_into().unwrap();
+    let expected = UrlKind::Gitlab(GitlabUrl {
+        host: "gitlab.com".to_owned(),
+        path: "abc/tools/def".to_owned(),
+    });
+    assert_eq!(result, expected);
+
+    let data = "https://gitlab.com/abc";
+    <&str as TryInto<UrlKind>>::try_into(data).unwrap_err();
+}
+
+#[test]
+fn url_kind_git() {
+    let data = "git@bitbucket.org:abc/def.git";
+    let result: UrlKind = data.try_into().unwrap();
+    let expected = UrlKind::Git(GitRepository {
+        url: data.to_owned(),
+    });
+    assert_eq!(result, expected);
+
+    // not clearly a repository
+    let data = "https://example.org/abc/def";
+    <&str as TryInto<UrlKind>>::try_into(data).unwrap_err();
+    // not a host repositories can be cloned from
+    let data = "git@example.org:abc/def.git";
+    <&str as TryInto<UrlKind>>::try_into(data).unwrap_err();
+}
+
+#[test]
+fn url_kind_npm_scoped() {
+    let data = "https://www.npmjs.com/package/@types/node";
+    let result: UrlKind = data.try_into().unwrap();
+    let expected = UrlKind::Npm(NpmUrl {
+        name: "@types/node".to_owned(),
+        version: None,
+    });
+    assert_eq!(result, expected);
+
+    let data = "https://www.npmjs.com/package/@babel/core/v/7.21.0";
+    let result: UrlKind = data.try_into().unwrap();
+    let expected = UrlKind::Npm(NpmUrl {
+        name: "@babel/core".to_owned(),
+        version: Some("7.21.0".to_owned()),
     });
     assert_eq!(result, expected);
+
+    let data = "https://npmjs.com/@types/node";
+    let result: UrlKind = data.try_into().unwrap();
+    let expected = UrlKind::Npm(NpmUrl {
+        name: "@types/node".to_owned(),
+        version: None,
+    });
+    assert_eq!(result, expected);
+
+    // a scope alone is not a package
+    let data = "https://www.npmjs.com/package/@types";
+    <&str as TryInto<UrlKind>>::try_into(data).unwrap_err();
+}
+
+#[test]
+fn npm_name_encoding() {
+    assert_eq!(encode_npm_name("chalk"), "chalk");
+    assert_eq!(encode_npm_name("@types/node"), "@types%2fnode");
+    assert_eq!(
+        encode_npm_name("@babel/plugin-transform-runtime"),
+        "@babel%2fplugin-transform-runtime"
+    );
+}
+
+#[test]
+fn url_kind_github_reference() {
+    let expected = |reference: &str, reference_with_path: bool| {
+        UrlKind::Github(GithubUrl {
+            name: "def".to_owned(),
+            owner: "abc".to_owned(),
+            reference: Some(reference.to_owned()),
+            reference_with_path,
+        })
+    };
+    let parse = |data: &str| -> UrlKind { data.try_into().unwrap() };
+
+    assert_eq!(
+        parse("https://github.com/abc/def/tree/v1.2.3"),
+        expected("v1.2.3", true)
+    );
+    assert_eq!(
+        parse("https://github.com/abc/def/tree/feature/thing"),
+        expected("feature/thing", true)
+    );
+    // where the branch ends and the path begins is left to the lookup
+    assert_eq!(
+        parse("https://github.com/abc/def/tree/main/src/lib"),
+        expected("main/src/lib", true)
+    );
+    assert_eq!(
+        parse("https://github.com/abc/def/blob/main/README.md"),
+        expected("main/README.md", true)
+    );
+    assert_eq!(
+        parse("https://github.com/abc/def/releases/tag/v2.0.0"),
+        expected("v2.0.0", false)
+    );
+    assert_eq!(
+        parse("https://github.com/abc/def/commit/0123abc"),
+        expected("0123abc", true)
+    );
+
+    // other pages of the repository are about its default branch
+    let result = parse("https://github.com/abc/def/issues/1");
+    assert!(matches!(
+        result,
+        UrlKind::Github(GithubUrl {
+            reference: None,
+            reference_with_path: false,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn hosts_listed_in_env() {
+    std::env::set_var("TEST_LISTED_HOSTS", " Git.Example.com, ,other.example ");
+    assert_eq!(
+        hosts_from_env("TEST_LISTED_HOSTS", "default.example"),
+        ["git.example.com", "other.example", "default.example"]
+    );
+    assert_eq!(
+        hosts_from_env("TEST_UNSET_HOSTS", "default.example"),
+        ["default.example"]
+    );
 }
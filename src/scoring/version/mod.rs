@@ -2,11 +2,19 @@
 mod tests;
 
 use num_traits::{One, SaturatingAdd};
-use semver::{Comparator, VersionReq};
+use once_cell::sync::OnceCell;
+use semver::{Comparator, Version, VersionReq};
+use serde::Deserialize;
 use std::{
     collections::HashMap,
+    fs, io,
     ops::{Bound, RangeBounds},
+    path::Path,
 };
+use walkdir::WalkDir;
+
+/// Lockfiles npm writes, in the order npm prefers them
+static LOCKFILE_NAMES: [&str; 2] = ["npm-shrinkwrap.json", "package-lock.json"];
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 #[cfg_attr(test, derive(strum::EnumDiscriminants))]
@@ -119,9 +127,10 @@ where
     let (total, pinned) = map.into_values().fold((0, 0), |(total, pinned), ver| {
         (
             total + 1,
-            match &ver.as_ref().parse() {
-                Ok(v) if versionreq_pinned(v) => pinned + 1,
-                _ => pinned,
+            if specifier_pinned(ver.as_ref()) {
+                pinned + 1
+            } else {
+                pinned
             },
         )
     });
@@ -133,9 +142,342 @@ where
     }
 }
 
+/// The dependency sections of a `package.json`, mapping package names to version requirements
+#[derive(Debug, Default)]
+pub(super) struct DependencySections {
+    pub(super) dependencies: HashMap<String, String>,
+    pub(super) dev_dependencies: HashMap<String, String>,
+    pub(super) peer_dependencies: HashMap<String, String>,
+    pub(super) optional_dependencies: HashMap<String, String>,
+}
+
+/// How much each dependency section counts towards GoodPinningPractice, and how much of the
+/// remaining score is made up for by a lockfile that pins every package in the tree
+#[derive(Debug, PartialEq)]
+pub(super) struct PinningWeights {
+    dependencies: f64,
+    dev_dependencies: f64,
+    peer_dependencies: f64,
+    optional_dependencies: f64,
+    lockfile: f64,
+}
+
+impl Default for PinningWeights {
+    /// Only the `dependencies` section counts, as they are what gets installed with the package
+    fn default() -> Self {
+        PinningWeights {
+            dependencies: 1.,
+            dev_dependencies: 0.,
+            peer_dependencies: 0.,
+            optional_dependencies: 0.,
+            lockfile: 0.5,
+        }
+    }
+}
+
+impl PinningWeights {
+    /// Set with `PINNING_WEIGHT_DEPENDENCIES`, `PINNING_WEIGHT_DEV_DEPENDENCIES`,
+    /// `PINNING_WEIGHT_PEER_DEPENDENCIES`, `PINNING_WEIGHT_OPTIONAL_DEPENDENCIES` and
+    /// `PINNING_LOCKFILE_CREDIT` (between 0 and 1)
+    pub(super) fn get() -> &'static PinningWeights {
+        static WEIGHTS: OnceCell<PinningWeights> = OnceCell::new();
+        WEIGHTS.get_or_init(|| {
+            let weight = |var, default: f64| {
+                std::env::var(var)
+                    .ok()
+                    .and_then(|w| w.parse::<f64>().ok())
+                    .filter(|w| *w >= 0.)
+                    .unwrap_or(default)
+            };
+            let default = PinningWeights::default();
+            PinningWeights {
+                dependencies: weight("PINNING_WEIGHT_DEPENDENCIES", default.dependencies),
+                dev_dependencies: weight(
+                    "PINNING_WEIGHT_DEV_DEPENDENCIES",
+                    default.dev_dependencies,
+                ),
+                peer_dependencies: weight(
+                    "PINNING_WEIGHT_PEER_DEPENDENCIES",
+                    default.peer_dependencies,
+                ),
+                optional_dependencies: weight(
+                    "PINNING_WEIGHT_OPTIONAL_DEPENDENCIES",
+                    default.optional_dependencies,
+                ),
+                lockfile: weight("PINNING_LOCKFILE_CREDIT", default.lockfile).min(1.),
+            }
+        })
+    }
+}
+
+/// Combine the pinning of each dependency section by its weight. Empty sections are left out,
+/// so a package is not rewarded or penalized for not having e.g. peer dependencies.
+pub(super) fn score_pinning(
+    sections: DependencySections,
+    weights: &PinningWeights,
+    lockfile_pins_tree: bool,
+) -> f64 {
+    let parts = [
+        (weights.dependencies, sections.dependencies),
+        (weights.dev_dependencies, sections.dev_dependencies),
+        (weights.peer_dependencies, sections.peer_dependencies),
+        (
+            weights.optional_dependencies,
+            sections.optional_dependencies,
+        ),
+    ];
+
+    let (weight, total) = parts
+        .into_iter()
+        .filter(|(weight, section)| *weight > 0. && !section.is_empty())
+        .fold((0., 0.), |(weights, total), (weight, section)| {
+            (
+                weights + weight,
+                total + weight * score_versionreq_pinned(section),
+            )
+        });
+
+    let declared = if weight == 0. { 1. } else { total / weight };
+    if lockfile_pins_tree {
+        declared + (1. - declared) * weights.lockfile
+    } else {
+        declared
+    }
+}
+
+/// A `package-lock.json` or `npm-shrinkwrap.json`.
+///
+/// Version 1 lockfiles only have the nested `dependencies`, version 3 only the flat `packages`,
+/// and version 2 has both.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct Lockfile {
+    #[serde(default)]
+    packages: HashMap<String, LockedPackage>,
+    #[serde(default)]
+    dependencies: HashMap<String, LockedDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    version: Option<String>,
+    #[serde(default)]
+    link: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedDependency {
+    version: Option<String>,
+    #[serde(default)]
+    dependencies: HashMap<String, LockedDependency>,
+}
+
+impl Lockfile {
+    /// Whether every package in the tree is locked to one version
+    pub(super) fn pins_full_tree(&self) -> bool {
+        fn dependencies_pinned(dependencies: &HashMap<String, LockedDependency>) -> bool {
+            dependencies.values().all(|d| {
+                d.version.as_deref().is_some_and(locked_version_pinned)
+                    && dependencies_pinned(&d.dependencies)
+            })
+        }
+
+        // the "" entry is the package itself
+        let packages_pinned = self.packages.iter().all(|(path, p)| {
+            path.is_empty() || p.link || p.version.as_deref().is_some_and(locked_version_pinned)
+        });
+
+        packages_pinned && dependencies_pinned(&self.dependencies)
+    }
+}
+
+/// Versions in lockfiles are exact, except for dependencies from outside the registry: those
+/// from git are pinned by their commit, and local files are part of the package
+fn locked_version_pinned(version: &str) -> bool {
+    Version::parse(version).is_ok() || version.starts_with("file:") || version.contains('#')
+}
+
+/// Find and read the lockfile at the top of an extracted package
+pub(super) fn find_lockfile<P: AsRef<Path>>(path: P) -> Option<Lockfile> {
+    let lockfiles: Vec<_> = WalkDir::new(path)
+        .max_depth(2)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| LOCKFILE_NAMES.iter().any(|name| e.file_name() == *name))
+        .collect();
+
+    let lockfile = LOCKFILE_NAMES
+        .iter()
+        .find_map(|name| lockfiles.iter().find(|e| e.file_name() == *name))?;
+
+    let file = fs::File::open(lockfile.path()).ok()?;
+    serde_json::from_reader(io::BufReader::new(file))
+        .map_err(|e| log::warn!("reading {}: {}", lockfile.path().display(), e))
+        .ok()
+}
+
+/// Where an npm dependency specifier says to get a package from.
+///
+/// See https://docs.npmjs.com/cli/v9/configuring-npm/package-json#dependencies
+#[derive(Debug, PartialEq, Eq)]
+enum Specifier<'a> {
+    /// a range or dist-tag, of the package named by an `npm:name@range` alias if there is one
+    Registry {
+        alias: Option<&'a str>,
+        range: &'a str,
+    },
+    Local,
+    Tarball,
+    Git(&'a str),
+}
+
+impl<'a> Specifier<'a> {
+    fn parse(spec: &'a str) -> Self {
+        let spec = spec.trim();
+
+        if let Some(alias) = spec.strip_prefix("npm:") {
+            // `npm:name@range`, where the name may be scoped (`npm:@scope/name@range`)
+            return match alias.rsplit_once('@') {
+                Some((name, range)) if !name.is_empty() => Specifier::Registry {
+                    alias: Some(name),
+                    range,
+                },
+                _ => Specifier::Registry {
+                    alias: Some(alias),
+                    range: "",
+                },
+            };
+        }
+        if spec.starts_with("file:") || spec.starts_with("link:") {
+            Specifier::Local
+        } else if GIT_PREFIXES.iter().any(|prefix| spec.starts_with(prefix)) {
+            Specifier::Git(spec)
+        } else if spec.starts_with("http://") || spec.starts_with("https://") {
+            Specifier::Tarball
+        } else if spec.contains('/') {
+            // `owner/repo` is shorthand for a GitHub repository, ranges never contain a `/`
+            Specifier::Git(spec)
+        } else {
+            Specifier::Registry {
+                alias: None,
+                range: spec,
+            }
+        }
+    }
+}
+
+/// Whether an npm dependency specifier pins the major and minor version
+fn specifier_pinned(spec: &str) -> bool {
+    match Specifier::parse(spec) {
+        // local paths are part of the package, and a tarball is a single version
+        Specifier::Local | Specifier::Tarball => true,
+        Specifier::Git(spec) => git_pinned(spec),
+        // fall back to `semver`'s own syntax (`>=1.2, <1.3`), anything else is a dist-tag like
+        // `latest`, which moves
+        Specifier::Registry { range, .. } => range_pinned(range)
+            .unwrap_or_else(|| range.parse().is_ok_and(|req| versionreq_pinned(&req))),
+    }
+}
+
+/// The name of the package in the registry that a dependency refers to, and the versions of it
+/// that satisfy the specifier (any of the requirements). `None` if it comes from somewhere else.
+///
+/// A dist-tag like `latest` could point at any version, so it is satisfied by all of them.
+pub fn registry_requirement<'a>(
+    name: &'a str,
+    spec: &'a str,
+) -> Option<(&'a str, Vec<VersionReq>)> {
+    let Specifier::Registry { alias, range } = Specifier::parse(spec) else {
+        return None;
+    };
+
+    let requirements = range
+        .split("||")
+        .map(|set| npm_comparators(set).map(|comparators| VersionReq { comparators }))
+        .collect::<Option<Vec<_>>>()
+        .or_else(|| range.parse().ok().map(|req| vec![req]))
+        .unwrap_or_else(|| vec![VersionReq::STAR]);
+
+    Some((alias.unwrap_or(name), requirements))
+}
+
+/// Prefixes of git dependencies, other than the `owner/repo` GitHub shorthand
+static GIT_PREFIXES: [&str; 6] = [
+    "git+",
+    "git://",
+    "github:",
+    "gitlab:",
+    "bitbucket:",
+    "gist:",
+];
+
+/// A git dependency is pinned by a commit, or by a range given with `#semver:<range>`.
+/// Branches and tags can be moved, so they aren't.
+fn git_pinned(spec: &str) -> bool {
+    match spec.rsplit_once('#') {
+        Some((_, committish)) => match committish.strip_prefix("semver:") {
+            Some(range) => range_pinned(range).unwrap_or(false),
+            None => committish.len() >= 7 && committish.chars().all(|c| c.is_ascii_hexdigit()),
+        },
+        None => false,
+    }
+}
+
+/// Whether an npm range pins the major and minor version, or `None` if it isn't a valid range.
+/// A union (`a || b`) is only pinned if every one of its ranges is.
+fn range_pinned(range: &str) -> Option<bool> {
+    let mut pinned = true;
+    for set in range.split("||") {
+        pinned &= comparators_pinned(&npm_comparators(set)?);
+    }
+    Some(pinned)
+}
+
+/// Parse a set of npm comparators, like `>=1.2.3 <2`, `1.2.x` or `1.2 - 1.4`
+fn npm_comparators(set: &str) -> Option<Vec<Comparator>> {
+    let tokens: Vec<_> = set.split_whitespace().collect();
+
+    // a hyphen range includes both ends
+    if let [low, "-", high] = tokens[..] {
+        let low = npm_comparator(&format!(">={low}"))?;
+        let high = npm_comparator(&format!("<={high}"))?;
+        return Some(low.into_iter().chain(high).collect());
+    }
+
+    let mut comparators = Vec::new();
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        // operators may be separated from their version by a space, like `>= 1.2.3`
+        let comparator = if token.chars().all(|c| "<>=~^".contains(c)) {
+            npm_comparator(&format!("{token}{}", tokens.next()?))?
+        } else {
+            npm_comparator(token)?
+        };
+        comparators.extend(comparator);
+    }
+    Some(comparators)
+}
+
+/// Parse a single npm comparator. `None` inside means it matches any version.
+fn npm_comparator(comparator: &str) -> Option<Option<Comparator>> {
+    let version_start = comparator.find(|c| !"<>=~^".contains(c))?;
+    let (op, version) = comparator.split_at(version_start);
+    let version = version.trim_start_matches(['v', '=']);
+
+    if ["*", "x", "X", ""].contains(&version) {
+        return Some(None);
+    }
+    // a version without an operator is exact in npm, while `semver` would treat it as `^`
+    let op = if op.is_empty() { "=" } else { op };
+    format!("{op}{version}").parse().ok().map(Some)
+}
+
 fn versionreq_pinned(req: &VersionReq) -> bool {
-    let major = req
-        .comparators
+    comparators_pinned(&req.comparators)
+}
+
+fn comparators_pinned(comparators: &[Comparator]) -> bool {
+    let major = comparators
         .iter()
         .fold(PinStatus::Any, |m, c| m.update(comparator_major_pinned(c)));
 
@@ -143,8 +485,7 @@ fn versionreq_pinned(req: &VersionReq) -> bool {
         return false;
     }
 
-    let minor = req
-        .comparators
+    let minor = comparators
         .iter()
         .fold(PinStatus::Any, |m, c| m.update(comparator_minor_pinned(c)));
 
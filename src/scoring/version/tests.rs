@@ -46,6 +46,114 @@ fn versionreq_pinned_map() {
     assert_eq!(score_versionreq_pinned(bad_parse), 0.);
 }
 
+#[test]
+fn specifier_pinned_ranges() {
+    // exact in npm, unlike in `semver`
+    assert!(specifier_pinned("1.2.3"));
+    assert!(specifier_pinned("v1.2.3"));
+    assert!(specifier_pinned("=1.2.3"));
+
+    assert!(specifier_pinned("1.2.x"));
+    assert!(specifier_pinned("1.2.X"));
+    assert!(specifier_pinned("1.2"));
+    assert!(specifier_pinned("~1.2.3"));
+    assert!(!specifier_pinned("1.x"));
+    assert!(!specifier_pinned("^1.2.3"));
+    assert!(specifier_pinned("^0.2.3"));
+
+    assert!(specifier_pinned(">=1.2.3 <1.3"));
+    assert!(specifier_pinned(">= 1.2.3 < 1.3"));
+    assert!(!specifier_pinned(">=1 <2"));
+    assert!(!specifier_pinned(">=1.2.3"));
+
+    // `semver` syntax still works
+    assert!(specifier_pinned("<1.3,>=1.2"));
+}
+
+#[test]
+fn specifier_pinned_hyphen() {
+    assert!(specifier_pinned("1.2.3 - 1.2.7"));
+    assert!(specifier_pinned("1.2 - 1.2"));
+    assert!(!specifier_pinned("1.2 - 1.4"));
+    assert!(!specifier_pinned("1.2.3 - 2.0.0"));
+}
+
+#[test]
+fn specifier_pinned_unions() {
+    assert!(specifier_pinned("1.2.3 || 1.2.5"));
+    // each branch pins major.minor, even if they differ
+    assert!(specifier_pinned("1.2.x || 2.4.x"));
+    assert!(!specifier_pinned("1.2.x || ^2.4.0"));
+    assert!(!specifier_pinned("1.2.x || "));
+}
+
+#[test]
+fn specifier_pinned_any() {
+    assert!(!specifier_pinned(""));
+    assert!(!specifier_pinned("*"));
+    assert!(!specifier_pinned("x"));
+    assert!(!specifier_pinned("latest"));
+    assert!(!specifier_pinned("next"));
+}
+
+#[test]
+fn specifier_pinned_other_sources() {
+    assert!(specifier_pinned("file:../local"));
+    assert!(specifier_pinned("link:../local"));
+    assert!(specifier_pinned(
+        "https://registry.npmjs.org/chalk/-/chalk-4.1.2.tgz"
+    ));
+
+    assert!(specifier_pinned("npm:chalk@4.1.2"));
+    assert!(specifier_pinned("npm:@babel/core@7.21.x"));
+    assert!(!specifier_pinned("npm:chalk@^4.1.2"));
+    assert!(!specifier_pinned("npm:chalk"));
+    assert!(!specifier_pinned("npm:@babel/core"));
+
+    assert!(specifier_pinned(
+        "git+https://github.com/fake/repo.git#0123456789abcdef0123456789abcdef01234567"
+    ));
+    assert!(specifier_pinned("github:fake/repo#abc1234"));
+    assert!(specifier_pinned("fake/repo#semver:~1.2.0"));
+    assert!(!specifier_pinned("fake/repo#semver:^1.2.0"));
+    assert!(!specifier_pinned("fake/repo#main"));
+    assert!(!specifier_pinned("fake/repo"));
+    assert!(!specifier_pinned("git://github.com/fake/repo.git"));
+}
+
+#[test]
+fn registry_requirements() {
+    let matches = |name, spec, version| {
+        let (resolved, requirements) = registry_requirement(name, spec).unwrap();
+        let version = Version::parse(version).unwrap();
+        (resolved, requirements.iter().any(|r| r.matches(&version)))
+    };
+
+    assert_eq!(matches("chalk", "^4.1.2", "4.3.0"), ("chalk", true));
+    assert_eq!(matches("chalk", "4.1.2", "4.3.0"), ("chalk", false));
+    assert_eq!(matches("chalk", "1.2 - 1.4", "1.4.9"), ("chalk", true));
+    assert_eq!(matches("chalk", "1.x || >=3 <4", "3.1.0"), ("chalk", true));
+    assert_eq!(matches("chalk", "1.x || >=3 <4", "2.0.0"), ("chalk", false));
+    assert_eq!(matches("chalk", "latest", "5.0.0"), ("chalk", true));
+    assert_eq!(matches("old", "npm:chalk@^4", "4.0.0"), ("chalk", true));
+    assert_eq!(
+        matches("core", "npm:@babel/core", "7.0.0"),
+        ("@babel/core", true)
+    );
+
+    assert_eq!(registry_requirement("local", "file:../local"), None);
+    assert_eq!(registry_requirement("repo", "fake/repo#main"), None);
+    assert_eq!(
+        registry_requirement("tarball", "https://example.com/a.tgz"),
+        None
+    );
+}
+
+#[quickcheck]
+fn specifier_exact_version_pinned(major: u64, minor: u64, patch: u64) -> bool {
+    specifier_pinned(&format!("{major}.{minor}.{patch}"))
+}
+
 impl quickcheck::Arbitrary for PinStatus {
     fn arbitrary(g: &mut quickcheck::Gen) -> Self {
         let disc = g
@@ -206,3 +314,125 @@ fn pinstatus_consolidate() {
         PinStatus::None
     );
 }
+
+fn sections(
+    dependencies: &[(&str, &str)],
+    dev_dependencies: &[(&str, &str)],
+) -> DependencySections {
+    let map = |deps: &[(&str, &str)]| {
+        deps.iter()
+            .map(|(name, req)| (name.to_string(), req.to_string()))
+            .collect()
+    };
+    DependencySections {
+        dependencies: map(dependencies),
+        dev_dependencies: map(dev_dependencies),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn pinning_sections_weighted() {
+    let weights = PinningWeights {
+        dependencies: 3.,
+        dev_dependencies: 1.,
+        ..Default::default()
+    };
+
+    let deps = || sections(&[("a", "=1.2.3"), ("b", "^1")], &[("c", "^2"), ("d", "^3")]);
+    // dependencies half pinned, dev dependencies not pinned
+    assert_eq!(score_pinning(deps(), &weights, false), 3. / 8.);
+    // only dependencies count by default
+    assert_eq!(
+        score_pinning(deps(), &PinningWeights::default(), false),
+        0.5
+    );
+
+    // empty sections don't count either way
+    let dev_only = sections(&[], &[("c", "=2.0.0")]);
+    assert_eq!(score_pinning(dev_only, &weights, false), 1.);
+    assert_eq!(score_pinning(Default::default(), &weights, false), 1.);
+}
+
+#[test]
+fn pinning_lockfile_credit() {
+    let weights = PinningWeights {
+        lockfile: 0.5,
+        ..Default::default()
+    };
+    let unpinned = || sections(&[("a", "^1")], &[]);
+    assert_eq!(score_pinning(unpinned(), &weights, false), 0.);
+    assert_eq!(score_pinning(unpinned(), &weights, true), 0.5);
+
+    let pinned = sections(&[("a", "=1.0.0")], &[]);
+    assert_eq!(score_pinning(pinned, &weights, true), 1.);
+}
+
+#[test]
+fn lockfile_v3_pinned() {
+    let data = r#"{
+  "name": "fake-package",
+  "version": "1.2.3",
+  "lockfileVersion": 3,
+  "requires": true,
+  "packages": {
+    "": {
+      "name": "fake-package",
+      "version": "1.2.3",
+      "dependencies": { "chalk": "^4.1.2" }
+    },
+    "node_modules/chalk": {
+      "version": "4.1.2",
+      "resolved": "https://registry.npmjs.org/chalk/-/chalk-4.1.2.tgz",
+      "dependencies": { "ansi-styles": "^4.1.0" }
+    },
+    "node_modules/ansi-styles": {
+      "version": "4.3.0"
+    },
+    "node_modules/local": {
+      "resolved": "packages/local",
+      "link": true
+    }
+  }
+}"#;
+    let lockfile: Lockfile = serde_json::from_str(data).unwrap();
+    assert!(lockfile.pins_full_tree());
+}
+
+#[test]
+fn lockfile_v1_nested() {
+    let data = r#"{
+  "name": "fake-package",
+  "version": "1.2.3",
+  "lockfileVersion": 1,
+  "dependencies": {
+    "chalk": {
+      "version": "4.1.2",
+      "requires": { "ansi-styles": "^4.1.0" },
+      "dependencies": {
+        "ansi-styles": { "version": "4.3.0" }
+      }
+    },
+    "from-git": {
+      "version": "github:fake/repo#0123456789abcdef0123456789abcdef01234567",
+      "from": "github:fake/repo"
+    }
+  }
+}"#;
+    let lockfile: Lockfile = serde_json::from_str(data).unwrap();
+    assert!(lockfile.pins_full_tree());
+
+    let data = r#"{
+  "lockfileVersion": 1,
+  "dependencies": {
+    "chalk": {
+      "version": "4.1.2",
+      "dependencies": {
+        "ansi-styles": { "version": "github:fake/ansi-styles" }
+      }
+    }
+  }
+}"#;
+    let lockfile: Lockfile = serde_json::from_str(data).unwrap();
+    assert!(!lockfile.pins_full_tree());
+}
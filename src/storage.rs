@@ -3,30 +3,79 @@ use gcloud_sdk::google_rest_apis::storage_v1::{
     self,
     buckets_api::{self, StoragePeriodBucketsPeriodGetParams},
     objects_api::{
-        self, StoragePeriodObjectsPeriodDeleteParams, StoragePeriodObjectsPeriodInsertParams,
-        StoragePeriodObjectsPeriodListParams,
+        self, StoragePeriodObjectsPeriodDeleteParams, StoragePeriodObjectsPeriodGetParams,
+        StoragePeriodObjectsPeriodInsertParams, StoragePeriodObjectsPeriodListParams,
     },
 };
+use once_cell::sync::OnceCell;
 
 const BUCKET_NAME: &str = "ece461-packages";
 
+/// A request to the storage bucket failed
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct StorageError(String);
+
+impl StorageError {
+    fn new(e: impl std::fmt::Display) -> Self {
+        StorageError(e.to_string())
+    }
+}
+
+/// Which archives are kept of packages ingested from npm, set with `NPM_ARCHIVE_FORMAT`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NpmArchives {
+    /// `zip`, the default: the package extracted and re-packed as a zip
+    Zip,
+    /// `tgz`: the tarball exactly as it was published
+    Tarball,
+    /// `both`: the tarball, with the zip kept beside it
+    Both,
+}
+
+impl NpmArchives {
+    pub fn get() -> Self {
+        static ARCHIVES: OnceCell<NpmArchives> = OnceCell::new();
+        *ARCHIVES.get_or_init(|| {
+            match std::env::var("NPM_ARCHIVE_FORMAT")
+                .as_deref()
+                .map(str::trim)
+            {
+                Ok("tgz") => NpmArchives::Tarball,
+                Ok("both") => NpmArchives::Both,
+                Ok("zip") | Ok("") | Err(_) => NpmArchives::Zip,
+                Ok(other) => {
+                    log::warn!("unknown NPM_ARCHIVE_FORMAT `{}`, keeping zips", other);
+                    NpmArchives::Zip
+                }
+            }
+        })
+    }
+}
+
 pub struct CloudStorage {
     bucket: String,
     client: gcloud_sdk::GoogleRestApi,
 }
 
 impl CloudStorage {
-    pub async fn new() -> Result<CloudStorage, Box<dyn std::error::Error>> {
-        let client = gcloud_sdk::GoogleRestApi::new().await?;
+    pub async fn new() -> Result<CloudStorage, StorageError> {
+        let client = gcloud_sdk::GoogleRestApi::new()
+            .await
+            .map_err(StorageError::new)?;
 
         let response = buckets_api::storage_buckets_get(
-            &client.create_google_storage_v1_config().await?,
+            &client
+                .create_google_storage_v1_config()
+                .await
+                .map_err(StorageError::new)?,
             StoragePeriodBucketsPeriodGetParams {
                 bucket: BUCKET_NAME.to_owned(),
                 ..StoragePeriodBucketsPeriodGetParams::default()
             },
         )
-        .await?;
+        .await
+        .map_err(StorageError::new)?;
 
         Ok(CloudStorage {
             bucket: response.name.unwrap(),
@@ -34,16 +83,12 @@ impl CloudStorage {
         })
     }
 
-    pub async fn put_object(
-        &self,
-        name: String,
-        content: Vec<u8>,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+    pub async fn put_object(&self, name: String, content: Vec<u8>) -> Result<String, StorageError> {
         let crc = crc32c::crc32c(&content).to_be_bytes();
         let crc_string = base64::engine::general_purpose::STANDARD_NO_PAD.encode(crc);
 
         let response = objects_api::storage_objects_insert_ext_bytes(
-            &self.client.create_google_storage_v1_config().await?,
+            &self.config().await?,
             StoragePeriodObjectsPeriodInsertParams {
                 bucket: self.bucket.to_owned(),
                 name: Some(name),
@@ -56,17 +101,35 @@ impl CloudStorage {
             None,
             content,
         )
-        .await?;
+        .await
+        .map_err(StorageError::new)?;
 
         assert_eq!(
-            base64::engine::general_purpose::STANDARD.decode(response.crc32c.unwrap())?,
+            base64::engine::general_purpose::STANDARD
+                .decode(response.crc32c.unwrap())
+                .map_err(StorageError::new)?,
             crc
         );
 
         Ok(response.media_link.unwrap())
     }
 
-    pub async fn delete_all(&self) -> Result<(), ()> {
+    pub async fn get_object(&self, name: String) -> Result<Vec<u8>, StorageError> {
+        let content = objects_api::storage_objects_get_bytes(
+            &self.config().await?,
+            StoragePeriodObjectsPeriodGetParams {
+                bucket: self.bucket.to_owned(),
+                object: name,
+                ..StoragePeriodObjectsPeriodGetParams::default()
+            },
+        )
+        .await
+        .map_err(StorageError::new)?;
+
+        Ok(content.into())
+    }
+
+    pub async fn delete_all(&self) -> Result<(), StorageError> {
         let names: Vec<_> = self
             .list_objects()
             .await?
@@ -82,33 +145,25 @@ impl CloudStorage {
         Ok(())
     }
 
-    async fn list_objects(&self) -> Result<Vec<storage_v1::Object>, ()> {
+    async fn list_objects(&self) -> Result<Vec<storage_v1::Object>, StorageError> {
         let response = objects_api::storage_objects_list(
-            &self
-                .client
-                .create_google_storage_v1_config()
-                .await
-                .map_err(|e| log::error!("while listing bucket: {}", e))?,
+            &self.config().await?,
             StoragePeriodObjectsPeriodListParams {
                 bucket: self.bucket.to_owned(),
                 ..StoragePeriodObjectsPeriodListParams::default()
             },
         )
         .await
-        .map_err(|e| log::error!("while listing bucket: {}", e))?
+        .map_err(StorageError::new)?
         .items
         .unwrap_or(vec![]);
 
         Ok(response)
     }
 
-    pub async fn delete_object(&self, name: String) -> Result<(), ()> {
+    pub async fn delete_object(&self, name: String) -> Result<(), StorageError> {
         objects_api::storage_objects_delete(
-            &self
-                .client
-                .create_google_storage_v1_config()
-                .await
-                .map_err(|e| log::error!("while deleting object: {}", e))?,
+            &self.config().await?,
             StoragePeriodObjectsPeriodDeleteParams {
                 bucket: self.bucket.to_owned(),
                 object: name,
@@ -116,8 +171,15 @@ impl CloudStorage {
             },
         )
         .await
-        .map_err(|e| log::error!("while deleting object: {}", e))?;
+        .map_err(StorageError::new)?;
 
         Ok(())
     }
+
+    async fn config(&self) -> Result<storage_v1::configuration::Configuration, StorageError> {
+        self.client
+            .create_google_storage_v1_config()
+            .await
+            .map_err(StorageError::new)
+    }
 }